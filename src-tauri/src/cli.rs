@@ -0,0 +1,435 @@
+//! Headless command line interface.
+//!
+//! `claude-desktop mcp ...`, `ext ...` and `config ...` run without opening a
+//! window, using the same config, server manager and extensions as the app.
+//! Results are printed to stdout as JSON; failures are printed to stderr as
+//! an `ErrorResponse` (`{"code", "message"}`) with exit status 1. Usage errors
+//! exit with status 2.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::error::{CommandResult, ErrorResponse};
+use crate::extensions;
+use crate::logging;
+use crate::mcp::config::McpConfig;
+use crate::mcp::gateway;
+use crate::mcp::recording::{self, Recording};
+use crate::mcp::validate;
+use crate::mcp::McpManager;
+
+const USAGE: &str = "\
+Usage: claude-desktop <command>
+
+Commands:
+  mcp list                                 List configured servers
+  mcp tools <server>                       List the tools of a server
+  mcp call <server> <tool> [--args JSON]   Call a tool
+  mcp read <server> <uri>                  Read a resource
+  mcp serve                                Serve all servers as one MCP server on stdio
+  mcp replay <recording> [--speed N]       Act as a server from a recorded session
+  ext install <file.dxt> [--id ID]         Install an extension
+  ext list                                 List installed extensions
+  config validate                          Check the config file
+
+Servers start when a command needs them and stop when it exits.
+Output is JSON. Set RUST_LOG (e.g. RUST_LOG=debug) for logs on stderr.";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    McpList,
+    McpTools {
+        server: String,
+    },
+    McpCall {
+        server: String,
+        tool: String,
+        arguments: Value,
+    },
+    McpRead {
+        server: String,
+        uri: String,
+    },
+    McpServe,
+    McpReplay {
+        file: PathBuf,
+        speed: Option<f64>,
+    },
+    ExtInstall {
+        file: PathBuf,
+        id: Option<String>,
+    },
+    ExtList,
+    ConfigValidate,
+    Help,
+}
+
+/// Runs a CLI command if `args` (without the program name) start with one.
+///
+/// Returns the exit status, or `None` to start the app.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        Some("mcp" | "ext" | "config" | "help" | "--help" | "-h") => {}
+        _ => return None,
+    }
+    #[cfg(windows)]
+    attach_console();
+
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return Some(2);
+        }
+    };
+    if command == Command::Help {
+        let _ = writeln!(std::io::stdout(), "{}", USAGE);
+        return Some(0);
+    }
+
+    logging::init_cli();
+    Some(tauri::async_runtime::block_on(execute(command)))
+}
+
+/// Release builds use the Windows GUI subsystem and start without a console,
+/// so output would go nowhere. Writes to the console of the calling shell.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when there is no parent console, e.g. when started by an MCP
+    // client with piped stdio, which is then used as is
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+fn parse(args: &[String]) -> Result<Command, String> {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (positional, options) = split_options(&words)?;
+
+    if positional.first() == Some(&"help")
+        || positional.iter().any(|w| matches!(*w, "--help" | "-h"))
+    {
+        return Ok(Command::Help);
+    }
+
+    let command = match positional.as_slice() {
+        ["mcp", "list"] => Command::McpList,
+        ["mcp", "tools", server] => Command::McpTools {
+            server: server.to_string(),
+        },
+        ["mcp", "call", server, tool] => {
+            let arguments = match option(&options, "--args") {
+                Some(json) => serde_json::from_str(json)
+                    .map_err(|e| format!("--args is not valid JSON: {}", e))?,
+                None => Value::Object(Default::default()),
+            };
+            Command::McpCall {
+                server: server.to_string(),
+                tool: tool.to_string(),
+                arguments,
+            }
+        }
+        ["mcp", "read", server, uri] => Command::McpRead {
+            server: server.to_string(),
+            uri: uri.to_string(),
+        },
+        ["mcp", "serve"] => Command::McpServe,
+        ["mcp", "replay", file] => {
+            let speed = match option(&options, "--speed") {
+                Some(speed) => match speed.parse::<f64>() {
+                    Ok(speed) if speed > 0.0 => Some(speed),
+                    _ => return Err(format!("--speed must be a positive number: {}", speed)),
+                },
+                None => None,
+            };
+            Command::McpReplay {
+                file: PathBuf::from(file),
+                speed,
+            }
+        }
+        ["ext", "install", file] => Command::ExtInstall {
+            file: PathBuf::from(file),
+            id: option(&options, "--id").map(str::to_string),
+        },
+        ["ext", "list"] => Command::ExtList,
+        ["config", "validate"] => Command::ConfigValidate,
+        _ => return Err(format!("Unknown command: {}", words.join(" "))),
+    };
+
+    let allowed: &[&str] = match command {
+        Command::McpCall { .. } => &["--args"],
+        Command::McpReplay { .. } => &["--speed"],
+        Command::ExtInstall { .. } => &["--id"],
+        _ => &[],
+    };
+    if let Some((name, _)) = options.iter().find(|(name, _)| !allowed.contains(name)) {
+        return Err(format!("Unknown option: {}", name));
+    }
+    Ok(command)
+}
+
+/// `--name value` pairs
+type Options<'a> = Vec<(&'a str, &'a str)>;
+
+/// Separates `--name value` pairs from positional arguments.
+fn split_options<'a>(words: &[&'a str]) -> Result<(Vec<&'a str>, Options<'a>), String> {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    let mut iter = words.iter();
+    while let Some(&word) = iter.next() {
+        if word.starts_with("--") && word != "--help" {
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", word))?;
+            options.push((word, *value));
+        } else {
+            positional.push(word);
+        }
+    }
+    Ok((positional, options))
+}
+
+fn option<'a>(options: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|(option, _)| *option == name)
+        .map(|(_, value)| *value)
+}
+
+async fn execute(command: Command) -> i32 {
+    let result = match command {
+        Command::ExtInstall { file, id } => install_extension(file, id).await,
+        Command::ExtList => extensions::extension_list().await.map(|list| output(&list)),
+        Command::ConfigValidate => {
+            let report = validate::validate_config_file();
+            let status = if report.valid { 0 } else { 1 };
+            return print_json(&report).unwrap_or(status);
+        }
+        Command::McpServe => return serve_stdio().await,
+        Command::McpReplay { file, speed } => match Recording::load(&file) {
+            Ok(recording) => {
+                recording::replay_stdio(recording, speed).await;
+                return 0;
+            }
+            Err(e) => Err(ErrorResponse::from(e)),
+        },
+        command => run_mcp(command).await,
+    };
+
+    match result {
+        Ok(value) => {
+            // A tool that reports an error fails the command, but its output is still printed
+            let failed = value.get("isError").and_then(Value::as_bool) == Some(true);
+            print_json(&value).unwrap_or(if failed { 1 } else { 0 })
+        }
+        Err(error) => {
+            eprintln!(
+                "{}",
+                serde_json::to_string_pretty(&error).unwrap_or_default()
+            );
+            1
+        }
+    }
+}
+
+async fn run_mcp(command: Command) -> CommandResult<Value> {
+    let manager = McpManager::new();
+    // Only the server a command uses is started
+    manager.set_start_on_demand(true);
+    if std::io::stdin().is_terminal() {
+        manager.set_confirm_handler(Arc::new(confirm));
+    }
+    manager.load_servers().await?;
+
+    let result = match command {
+        Command::McpList => Ok(output(&manager.list_servers().await)),
+        Command::McpTools { server } => manager.list_tools(&server).await.map(|t| output(&t)),
+        Command::McpCall {
+            server,
+            tool,
+            arguments,
+        } => manager.call_tool(&server, &tool, arguments).await,
+        Command::McpRead { server, uri } => manager.read_resource(&server, &uri).await,
+        _ => unreachable!("not an mcp command"),
+    };
+
+    manager.stop_all().await;
+    Ok(result?)
+}
+
+/// Stdio shim for the gateway: relays stdin and stdout to the app's gateway
+/// socket, or serves the configured servers from this process when the app
+/// is not running.
+async fn serve_stdio() -> i32 {
+    let config = McpConfig::load().map(|c| c.gateway).unwrap_or_default();
+    #[cfg(unix)]
+    if let Ok(stream) = tokio::net::UnixStream::connect(gateway::socket_path(&config)).await {
+        return match relay(stream).await {
+            Ok(()) => 0,
+            Err(e) => {
+                tracing::error!("MCP gateway connection failed: {}", e);
+                1
+            }
+        };
+    }
+    #[cfg(not(unix))]
+    let _ = config;
+
+    tracing::info!("Claude Desktop is not running; serving MCP servers from this process");
+    let manager = McpManager::new();
+    manager.set_start_on_demand(true);
+    if let Err(e) = manager.load_servers().await {
+        tracing::error!("{}", e);
+        return 1;
+    }
+    // stdin carries the protocol, so tools that ask for approval are denied
+    let manager = Arc::new(RwLock::new(manager));
+    let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    gateway::serve(stdin, tokio::io::stdout(), manager.clone()).await;
+    manager.read().await.stop_all().await;
+    0
+}
+
+#[cfg(unix)]
+async fn relay(stream: tokio::net::UnixStream) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let token = std::fs::read_to_string(gateway::token_path())?;
+    let (mut reader, mut writer) = stream.into_split();
+    writer
+        .write_all(gateway::auth_line(token.trim()).as_bytes())
+        .await?;
+    let upstream = async {
+        tokio::io::copy(&mut tokio::io::stdin(), &mut writer).await?;
+        // Lets the gateway finish running requests and close the connection
+        writer.shutdown().await
+    };
+    let mut stdout = tokio::io::stdout();
+    let downstream = tokio::io::copy(&mut reader, &mut stdout);
+    tokio::try_join!(upstream, downstream)?;
+    Ok(())
+}
+
+async fn install_extension(file: PathBuf, id: Option<String>) -> CommandResult<Value> {
+    let id = match id {
+        Some(id) => id,
+        None => file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Cannot derive an extension id from {}", file.display()))?,
+    };
+    let data = std::fs::read(&file)?;
+    let installed = extensions::install(id, data)?;
+    Ok(output(&installed))
+}
+
+/// Asks on the terminal before starting an untrusted project server or
+/// running a tool whose permission is `ask`.
+fn confirm(title: String, message: String) -> bool {
+    eprint!("{}\n{}\nAllow? [y/N] ", title, message);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn output<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Prints `value` to stdout. Returns an exit status if that failed.
+fn print_json<T: Serialize>(value: &T) -> Option<i32> {
+    let json = match serde_json::to_string_pretty(value) {
+        Ok(json) => json,
+        Err(e) => {
+            let error = ErrorResponse::from(e);
+            eprintln!(
+                "{}",
+                serde_json::to_string_pretty(&error).unwrap_or_default()
+            );
+            return Some(1);
+        }
+    };
+    let mut stdout = std::io::stdout().lock();
+    // A closed pipe (e.g. `| head`) only shows in the exit status
+    match writeln!(stdout, "{}", json) {
+        Ok(()) => None,
+        Err(_) => Some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_words(line: &str) -> Result<Command, String> {
+        let args: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_words("mcp list"), Ok(Command::McpList));
+        assert_eq!(
+            parse_words(r#"mcp call fs read_file --args {"path":"/tmp"}"#),
+            Ok(Command::McpCall {
+                server: "fs".to_string(),
+                tool: "read_file".to_string(),
+                arguments: serde_json::json!({ "path": "/tmp" }),
+            })
+        );
+        assert_eq!(
+            parse_words("mcp call fs list"),
+            Ok(Command::McpCall {
+                server: "fs".to_string(),
+                tool: "list".to_string(),
+                arguments: serde_json::json!({}),
+            })
+        );
+        assert_eq!(
+            parse_words("ext install ./fs.dxt --id ant.fs"),
+            Ok(Command::ExtInstall {
+                file: PathBuf::from("./fs.dxt"),
+                id: Some("ant.fs".to_string()),
+            })
+        );
+        assert_eq!(parse_words("mcp serve"), Ok(Command::McpServe));
+        assert_eq!(
+            parse_words("mcp replay fs.jsonl --speed 2"),
+            Ok(Command::McpReplay {
+                file: PathBuf::from("fs.jsonl"),
+                speed: Some(2.0),
+            })
+        );
+        assert_eq!(parse_words("config validate"), Ok(Command::ConfigValidate));
+        assert_eq!(parse_words("mcp --help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_words("mcp call fs read_file --args {oops").is_err());
+        assert!(parse_words("mcp call fs read_file --args").is_err());
+        assert!(parse_words("mcp list --args {}").is_err());
+        assert!(parse_words("mcp tools").is_err());
+        assert!(parse_words("mcp replay fs.jsonl --speed 0").is_err());
+        assert!(parse_words("ext remove fs").is_err());
+    }
+
+    #[test]
+    fn test_app_launch_is_not_a_command() {
+        assert_eq!(run(&[]), None);
+        assert_eq!(run(&["--some-app-flag".to_string()]), None);
+    }
+}
@@ -1,11 +1,16 @@
 //! Unified error types for the application.
 //!
-//! This module provides a centralized error handling system.
-#![allow(dead_code)]
+//! This module provides a centralized error handling system. Tauri commands
+//! return `CommandResult`, so the frontend receives an `ErrorResponse` with a
+//! stable `code` (e.g. `MCP_TIMEOUT`, `EXT_NOT_FOUND`) next to the message.
 
 use serde::Serialize;
+use serde_json::Value;
 use std::fmt;
 
+use crate::mcp::jsonrpc::ErrorObject;
+use crate::mcp::queue::QueueFull;
+
 /// Application-wide error type
 #[derive(Debug)]
 pub enum AppError {
@@ -19,12 +24,14 @@ pub enum AppError {
     Io(std::io::Error),
     /// JSON serialization/deserialization errors
     Json(serde_json::Error),
+    /// HTTP proxy request errors
+    Http(String),
     /// Generic error with message
     Other(String),
 }
 
-/// MCP-specific errors
-#[derive(Debug)]
+/// MCP-specific errors, from the manager and from talking to a server
+#[derive(Debug, Clone, PartialEq)]
 pub enum McpError {
     /// Server not found
     ServerNotFound(String),
@@ -37,7 +44,17 @@ pub enum McpError {
     /// Request cancelled
     Cancelled,
     /// JSON-RPC error from server
-    JsonRpc { code: i64, message: String },
+    JsonRpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    /// Too many requests in flight and queued
+    Busy(String),
+    /// Tool is disabled or not exposed under the requested name
+    ToolUnavailable(String),
+    /// Tool call denied by policy or rejected by the user
+    PermissionDenied(String),
     /// Communication error
     Communication(String),
 }
@@ -51,8 +68,6 @@ pub enum ExtensionError {
     InvalidManifest { id: String, reason: String },
     /// Installation failed
     InstallFailed { id: String, reason: String },
-    /// Missing required configuration
-    MissingConfig { id: String, field: String },
     /// Invalid extension package
     InvalidPackage(String),
 }
@@ -60,24 +75,28 @@ pub enum ExtensionError {
 /// Configuration errors
 #[derive(Debug)]
 pub enum ConfigError {
-    /// Config file not found
-    NotFound(String),
+    /// Profile not found
+    ProfileNotFound(String),
     /// Failed to read config
     ReadFailed(String),
     /// Failed to write config
     WriteFailed(String),
     /// Invalid config format
     InvalidFormat(String),
+    /// A section (or server entry) of the config has the wrong shape
+    ParseError { key: String, reason: String },
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AppError::Mcp(e) => write!(f, "MCP error: {}", e),
-            AppError::Extension(e) => write!(f, "Extension error: {}", e),
-            AppError::Config(e) => write!(f, "Config error: {}", e),
+            // The code already tells the kind, so these keep their own message
+            AppError::Mcp(e) => write!(f, "{}", e),
+            AppError::Extension(e) => write!(f, "{}", e),
+            AppError::Config(e) => write!(f, "{}", e),
             AppError::Io(e) => write!(f, "I/O error: {}", e),
             AppError::Json(e) => write!(f, "JSON error: {}", e),
+            AppError::Http(msg) => write!(f, "{}", msg),
             AppError::Other(msg) => write!(f, "{}", msg),
         }
     }
@@ -100,9 +119,12 @@ impl fmt::Display for McpError {
                 write!(f, "Request '{}' timed out after {}s", method, timeout_secs)
             }
             McpError::Cancelled => write!(f, "Request cancelled"),
-            McpError::JsonRpc { code, message } => {
+            McpError::JsonRpc { code, message, .. } => {
                 write!(f, "JSON-RPC error {}: {}", code, message)
             }
+            McpError::Busy(msg)
+            | McpError::ToolUnavailable(msg)
+            | McpError::PermissionDenied(msg) => write!(f, "{}", msg),
             McpError::Communication(msg) => write!(f, "Communication error: {}", msg),
         }
     }
@@ -118,9 +140,6 @@ impl fmt::Display for ExtensionError {
             ExtensionError::InstallFailed { id, reason } => {
                 write!(f, "Failed to install '{}': {}", id, reason)
             }
-            ExtensionError::MissingConfig { id, field } => {
-                write!(f, "Missing required config '{}' for '{}'", field, id)
-            }
             ExtensionError::InvalidPackage(reason) => {
                 write!(f, "Invalid extension package: {}", reason)
             }
@@ -131,10 +150,13 @@ impl fmt::Display for ExtensionError {
 impl fmt::Display for ConfigError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ConfigError::NotFound(path) => write!(f, "Config file not found: {}", path),
+            ConfigError::ProfileNotFound(name) => write!(f, "Profile '{}' not found", name),
             ConfigError::ReadFailed(reason) => write!(f, "Failed to read config: {}", reason),
             ConfigError::WriteFailed(reason) => write!(f, "Failed to write config: {}", reason),
             ConfigError::InvalidFormat(reason) => write!(f, "Invalid config format: {}", reason),
+            ConfigError::ParseError { key, reason } => {
+                write!(f, "Invalid '{}' in config: {}", key, reason)
+            }
         }
     }
 }
@@ -163,6 +185,32 @@ impl From<McpError> for AppError {
     }
 }
 
+impl From<ErrorObject> for McpError {
+    fn from(error: ErrorObject) -> Self {
+        McpError::JsonRpc {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
+
+impl From<QueueFull> for McpError {
+    fn from(full: QueueFull) -> Self {
+        McpError::Busy(format!(
+            "Server is busy: {} requests in flight and {} queued (maxInFlight/maxQueued reached)",
+            full.max_in_flight, full.max_queued
+        ))
+    }
+}
+
+// The client's setup steps report plain messages
+impl From<McpError> for String {
+    fn from(e: McpError) -> Self {
+        e.to_string()
+    }
+}
+
 impl From<ExtensionError> for AppError {
     fn from(e: ExtensionError) -> Self {
         AppError::Extension(e)
@@ -187,33 +235,91 @@ impl From<&str> for AppError {
     }
 }
 
+impl AppError {
+    /// Structured details of the error, e.g. the `data` of a server's JSON-RPC error
+    pub fn data(&self) -> Option<&Value> {
+        match self {
+            AppError::Mcp(McpError::JsonRpc { data, .. }) => data.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Stable code for the frontend to branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Mcp(e) => match e {
+                McpError::ServerNotFound(_) => "MCP_SERVER_NOT_FOUND",
+                McpError::SpawnFailed { .. } => "MCP_SPAWN_FAILED",
+                McpError::InitFailed { .. } => "MCP_INIT_FAILED",
+                McpError::Timeout { .. } => "MCP_TIMEOUT",
+                McpError::Cancelled => "MCP_CANCELLED",
+                McpError::JsonRpc { .. } => "MCP_RPC_ERROR",
+                McpError::Busy(_) => "MCP_BUSY",
+                McpError::ToolUnavailable(_) => "MCP_TOOL_UNAVAILABLE",
+                McpError::PermissionDenied(_) => "MCP_PERMISSION_DENIED",
+                McpError::Communication(_) => "MCP_ERROR",
+            },
+            AppError::Extension(e) => match e {
+                ExtensionError::NotFound(_) => "EXT_NOT_FOUND",
+                ExtensionError::InvalidManifest { .. } => "EXT_INVALID_MANIFEST",
+                ExtensionError::InvalidPackage(_) => "EXT_INVALID_PACKAGE",
+                ExtensionError::InstallFailed { .. } => "EXT_ERROR",
+            },
+            AppError::Config(ConfigError::ProfileNotFound(_)) => "CONFIG_PROFILE_NOT_FOUND",
+            AppError::Config(ConfigError::ParseError { .. }) => "CONFIG_PARSE_ERROR",
+            AppError::Config(_) => "CONFIG_ERROR",
+            AppError::Io(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => "FILE_NOT_FOUND",
+                std::io::ErrorKind::PermissionDenied => "FILE_PERMISSION_DENIED",
+                _ => "IO_ERROR",
+            },
+            AppError::Json(_) => "JSON_ERROR",
+            AppError::Http(_) => "HTTP_ERROR",
+            AppError::Other(_) => "UNKNOWN_ERROR",
+        }
+    }
+}
+
 // Serialize for Tauri command responses
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub code: String,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
 impl From<AppError> for ErrorResponse {
     fn from(e: AppError) -> Self {
-        let (code, message) = match &e {
-            AppError::Mcp(McpError::ServerNotFound(_)) => ("MCP_SERVER_NOT_FOUND", e.to_string()),
-            AppError::Mcp(McpError::Timeout { .. }) => ("MCP_TIMEOUT", e.to_string()),
-            AppError::Mcp(_) => ("MCP_ERROR", e.to_string()),
-            AppError::Extension(ExtensionError::NotFound(_)) => ("EXT_NOT_FOUND", e.to_string()),
-            AppError::Extension(_) => ("EXT_ERROR", e.to_string()),
-            AppError::Config(_) => ("CONFIG_ERROR", e.to_string()),
-            AppError::Io(_) => ("IO_ERROR", e.to_string()),
-            AppError::Json(_) => ("JSON_ERROR", e.to_string()),
-            AppError::Other(_) => ("UNKNOWN_ERROR", e.to_string()),
-        };
         ErrorResponse {
-            code: code.to_string(),
-            message,
+            code: e.code().to_string(),
+            message: e.to_string(),
+            data: e.data().cloned(),
         }
     }
 }
 
+// Lets commands use `?` on every error that converts into AppError;
+// helpers that still report a plain message map to UNKNOWN_ERROR
+macro_rules! error_response_from {
+    ($($error:ty),*) => {
+        $(impl From<$error> for ErrorResponse {
+            fn from(e: $error) -> Self {
+                AppError::from(e).into()
+            }
+        })*
+    };
+}
+
+error_response_from!(
+    McpError,
+    ExtensionError,
+    ConfigError,
+    std::io::Error,
+    serde_json::Error,
+    String
+);
+
 // For Tauri command compatibility - convert to String
 impl From<AppError> for String {
     fn from(e: AppError) -> Self {
@@ -223,3 +329,68 @@ impl From<AppError> for String {
 
 /// Result type alias using AppError
 pub type AppResult<T> = Result<T, AppError>;
+
+/// Result type of Tauri commands
+pub type CommandResult<T> = Result<T, ErrorResponse>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let timeout: AppError = McpError::Timeout {
+            method: "tools/call".to_string(),
+            timeout_secs: 30,
+        }
+        .into();
+        let response = ErrorResponse::from(timeout);
+        assert_eq!(response.code, "MCP_TIMEOUT");
+        assert_eq!(response.message, "Request 'tools/call' timed out after 30s");
+
+        let busy: AppError = McpError::from(QueueFull {
+            max_in_flight: 4,
+            max_queued: 16,
+        })
+        .into();
+        assert_eq!(busy.code(), "MCP_BUSY");
+
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(AppError::from(missing).code(), "FILE_NOT_FOUND");
+        assert_eq!(
+            AppError::from(ExtensionError::NotFound("x".to_string())).code(),
+            "EXT_NOT_FOUND"
+        );
+        assert_eq!(
+            ErrorResponse::from("Workspace already added".to_string()).code,
+            "UNKNOWN_ERROR"
+        );
+    }
+
+    #[test]
+    fn test_error_response_json() {
+        let response =
+            ErrorResponse::from(AppError::from(McpError::ServerNotFound("fs".to_string())));
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({
+                "code": "MCP_SERVER_NOT_FOUND",
+                "message": "Server 'fs' not found"
+            })
+        );
+
+        let rpc = ErrorResponse::from(AppError::from(McpError::from(ErrorObject {
+            code: -32602,
+            message: "Invalid path".to_string(),
+            data: Some(serde_json::json!({"path": "/etc"})),
+        })));
+        assert_eq!(
+            serde_json::to_value(&rpc).unwrap(),
+            serde_json::json!({
+                "code": "MCP_RPC_ERROR",
+                "message": "JSON-RPC error -32602: Invalid path",
+                "data": {"path": "/etc"}
+            })
+        );
+    }
+}
@@ -4,8 +4,12 @@ use std::fs;
 use std::io::Cursor;
 use std::path::PathBuf;
 use tauri::command;
+use tracing::{debug, info, warn};
 use zip::ZipArchive;
 
+use crate::error::{AppResult, CommandResult, ExtensionError};
+use crate::mcp::config::SandboxConfig;
+
 /// Extension manifest structure (from .dxt file)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtensionManifest {
@@ -47,6 +51,9 @@ pub struct ExtensionSettings {
     pub is_enabled: bool,
     #[serde(default)]
     pub user_config: HashMap<String, serde_json::Value>,
+    /// Run the extension's MCP server in a sandbox (Linux only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
 }
 
 fn default_true() -> bool {
@@ -88,6 +95,10 @@ pub struct ExtensionMcpServer {
     pub command: String,
     pub args: Vec<String>,
     pub env: std::collections::HashMap<String, String>,
+    /// Sandbox with the extension directory readable and `directory`
+    /// user_config values writable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
 }
 
 /// Installed extension info
@@ -99,18 +110,26 @@ pub struct InstalledExtension {
     pub enabled: bool,
 }
 
+/// Directory that holds the extensions and their settings
+fn get_claude_dir() -> PathBuf {
+    // Tests must not read the user's extensions
+    #[cfg(test)]
+    let base = std::env::temp_dir().join("claude-desktop-tests");
+    #[cfg(not(test))]
+    let base = dirs::home_dir()
+        .expect("Could not find home directory")
+        .join(".config");
+    base.join("Claude")
+}
+
 /// Get extensions directory path
 fn get_extensions_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".config").join("Claude").join("extensions")
+    get_claude_dir().join("extensions")
 }
 
 /// Get extension settings directory path
 fn get_extension_settings_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".config")
-        .join("Claude")
-        .join("extension-settings")
+    get_claude_dir().join("extension-settings")
 }
 
 /// Install extension from binary data (.dxt file content)
@@ -118,22 +137,33 @@ fn get_extension_settings_dir() -> PathBuf {
 pub async fn extension_install(
     extension_id: String,
     dxt_data: Vec<u8>,
-) -> Result<InstalledExtension, String> {
+) -> CommandResult<InstalledExtension> {
+    Ok(install(extension_id, dxt_data)?)
+}
+
+/// Extracts a .dxt archive into the extensions directory
+pub fn install(extension_id: String, dxt_data: Vec<u8>) -> AppResult<InstalledExtension> {
     let extensions_dir = get_extensions_dir();
     let extension_dir = extensions_dir.join(&extension_id);
+    let install_failed = |reason: String| ExtensionError::InstallFailed {
+        id: extension_id.clone(),
+        reason,
+    };
 
     // Create directories if needed
-    fs::create_dir_all(&extension_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    fs::create_dir_all(&extension_dir)
+        .map_err(|e| install_failed(format!("Failed to create directory: {}", e)))?;
 
     // Extract .dxt file (it's a zip archive)
     let cursor = Cursor::new(dxt_data);
-    let mut archive =
-        ZipArchive::new(cursor).map_err(|e| format!("Failed to read dxt archive: {}", e))?;
+    let mut archive = ZipArchive::new(cursor).map_err(|e| {
+        ExtensionError::InvalidPackage(format!("Failed to read dxt archive: {}", e))
+    })?;
 
     for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        let mut file = archive.by_index(i).map_err(|e| {
+            ExtensionError::InvalidPackage(format!("Failed to read archive entry: {}", e))
+        })?;
         let outpath = match file.enclosed_name() {
             Some(path) => extension_dir.join(path),
             None => continue,
@@ -141,27 +171,32 @@ pub async fn extension_install(
 
         if file.name().ends_with('/') {
             fs::create_dir_all(&outpath)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
+                .map_err(|e| install_failed(format!("Failed to create directory: {}", e)))?;
         } else {
             if let Some(p) = outpath.parent() {
                 if !p.exists() {
-                    fs::create_dir_all(p)
-                        .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+                    fs::create_dir_all(p).map_err(|e| {
+                        install_failed(format!("Failed to create parent directory: {}", e))
+                    })?;
                 }
             }
-            let mut outfile =
-                fs::File::create(&outpath).map_err(|e| format!("Failed to create file: {}", e))?;
+            let mut outfile = fs::File::create(&outpath)
+                .map_err(|e| install_failed(format!("Failed to create file: {}", e)))?;
             std::io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to write file: {}", e))?;
+                .map_err(|e| install_failed(format!("Failed to write file: {}", e)))?;
         }
     }
 
     // Read manifest.json
     let manifest_path = extension_dir.join("manifest.json");
-    let manifest_content = fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("Failed to read manifest.json: {}", e))?;
-    let manifest: ExtensionManifest = serde_json::from_str(&manifest_content)
-        .map_err(|e| format!("Failed to parse manifest.json: {}", e))?;
+    let manifest_content = fs::read_to_string(&manifest_path).map_err(|e| {
+        ExtensionError::InvalidPackage(format!("Failed to read manifest.json: {}", e))
+    })?;
+    let manifest: ExtensionManifest =
+        serde_json::from_str(&manifest_content).map_err(|e| ExtensionError::InvalidManifest {
+            id: extension_id.clone(),
+            reason: e.to_string(),
+        })?;
 
     // Create settings file if not exists
     let settings_dir = get_extension_settings_dir();
@@ -181,7 +216,7 @@ pub async fn extension_install(
 
 /// Get list of installed extensions
 #[command]
-pub async fn extension_list() -> Result<Vec<InstalledExtension>, String> {
+pub async fn extension_list() -> CommandResult<Vec<InstalledExtension>> {
     let extensions_dir = get_extensions_dir();
     let settings_dir = get_extension_settings_dir();
 
@@ -191,8 +226,7 @@ pub async fn extension_list() -> Result<Vec<InstalledExtension>, String> {
 
     let mut extensions = Vec::new();
 
-    let entries = fs::read_dir(&extensions_dir)
-        .map_err(|e| format!("Failed to read extensions directory: {}", e))?;
+    let entries = fs::read_dir(&extensions_dir)?;
 
     for entry in entries {
         let entry = match entry {
@@ -219,10 +253,7 @@ pub async fn extension_list() -> Result<Vec<InstalledExtension>, String> {
         let manifest_content = match fs::read_to_string(&manifest_path) {
             Ok(c) => c,
             Err(e) => {
-                eprintln!(
-                    "[Extensions] Failed to read manifest for {}: {}",
-                    extension_id, e
-                );
+                warn!("Failed to read manifest for {}: {}", extension_id, e);
                 continue;
             }
         };
@@ -230,10 +261,7 @@ pub async fn extension_list() -> Result<Vec<InstalledExtension>, String> {
         let manifest: ExtensionManifest = match serde_json::from_str(&manifest_content) {
             Ok(m) => m,
             Err(e) => {
-                eprintln!(
-                    "[Extensions] Failed to parse manifest for {}: {}",
-                    extension_id, e
-                );
+                warn!("Failed to parse manifest for {}: {}", extension_id, e);
                 continue;
             }
         };
@@ -263,13 +291,12 @@ pub async fn extension_list() -> Result<Vec<InstalledExtension>, String> {
 
 /// Delete an extension
 #[command]
-pub async fn extension_delete(extension_id: String) -> Result<(), String> {
+pub async fn extension_delete(extension_id: String) -> CommandResult<()> {
     let extensions_dir = get_extensions_dir();
     let extension_dir = extensions_dir.join(&extension_id);
 
     if extension_dir.exists() {
-        fs::remove_dir_all(&extension_dir)
-            .map_err(|e| format!("Failed to delete extension: {}", e))?;
+        fs::remove_dir_all(&extension_dir)?;
     }
 
     // Also remove settings
@@ -284,7 +311,7 @@ pub async fn extension_delete(extension_id: String) -> Result<(), String> {
 
 /// Set extension enabled/disabled
 #[command]
-pub async fn extension_set_enabled(extension_id: String, enabled: bool) -> Result<(), String> {
+pub async fn extension_set_enabled(extension_id: String, enabled: bool) -> CommandResult<()> {
     let settings_dir = get_extension_settings_dir();
     fs::create_dir_all(&settings_dir).ok();
 
@@ -294,15 +321,14 @@ pub async fn extension_set_enabled(extension_id: String, enabled: bool) -> Resul
     fs::write(
         &settings_path,
         serde_json::to_string_pretty(&settings).unwrap(),
-    )
-    .map_err(|e| format!("Failed to save settings: {}", e))?;
+    )?;
 
     Ok(())
 }
 
 /// Get extensions directory path
 #[command]
-pub async fn extension_get_path() -> Result<String, String> {
+pub async fn extension_get_path() -> CommandResult<String> {
     Ok(get_extensions_dir().to_string_lossy().to_string())
 }
 
@@ -338,12 +364,15 @@ fn resolve_user_config_placeholder(
                     serde_json::Value::Array(arr) => {
                         return arr
                             .iter()
-                            .filter_map(|v| v.as_str().map(|s| arg.replace(placeholder, s)))
+                            .filter_map(|v| {
+                                v.as_str()
+                                    .map(|s| arg.replace(placeholder, &escape_expansion(s)))
+                            })
                             .collect();
                     }
                     // For string values, simple replacement
                     serde_json::Value::String(s) => {
-                        return vec![arg.replace(placeholder, s)];
+                        return vec![arg.replace(placeholder, &escape_expansion(s))];
                     }
                     // For other types, convert to string
                     _ => {
@@ -360,26 +389,80 @@ fn resolve_user_config_placeholder(
     vec![arg.to_string()]
 }
 
+/// Escapes `$` in a substituted value so the `${...}` expansion applied at
+/// spawn time keeps it literal (API keys and paths may contain `$`).
+fn escape_expansion(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+/// Resolves `${__dirname}` and `${user_config.*}` in a value that cannot
+/// expand to several values (the command or an env value), so array values
+/// are joined with commas. Unset user_config values fall back to the
+/// manifest default; if there is none, the key is returned as the error.
+fn resolve_value_placeholders(
+    value: &str,
+    ext: &InstalledExtension,
+    settings: &ExtensionSettings,
+) -> Result<String, String> {
+    const PREFIX: &str = "${user_config.";
+    let mut output = value.replace("${__dirname}", &escape_expansion(&ext.path));
+    // Search after each replacement so values containing placeholders are kept as-is
+    let mut from = 0;
+    while let Some(offset) = output[from..].find(PREFIX) {
+        let start = from + offset;
+        let Some(len) = output[start..].find('}') else {
+            break;
+        };
+        let key = output[start + PREFIX.len()..start + len].to_string();
+        let default = ext
+            .manifest
+            .user_config
+            .as_ref()
+            .and_then(|fields| fields.get(&key))
+            .and_then(|field| field.default.as_ref());
+        let replacement = match settings.user_config.get(&key).or(default) {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| v.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(other) => other.to_string(),
+            None => return Err(key),
+        };
+        let replacement = escape_expansion(&replacement);
+        output.replace_range(start..start + len + 1, &replacement);
+        from = start + replacement.len();
+    }
+    Ok(output)
+}
+
+fn is_required_user_config(ext: &InstalledExtension, key: &str) -> bool {
+    ext.manifest
+        .user_config
+        .as_ref()
+        .and_then(|fields| fields.get(key))
+        .is_some_and(|field| field.required.unwrap_or(false))
+}
+
 /// Get MCP server configs from all enabled extensions
 #[command]
-pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, String> {
-    println!("[Extensions] Getting MCP servers from extensions...");
+pub async fn extension_get_mcp_servers() -> CommandResult<Vec<ExtensionMcpServer>> {
+    debug!("Getting MCP servers from extensions...");
     let extensions = extension_list().await?;
-    println!(
-        "[Extensions] Found {} installed extensions",
-        extensions.len()
-    );
+    debug!("Found {} installed extensions", extensions.len());
     let mut mcp_servers = Vec::new();
 
     for ext in extensions {
-        println!(
-            "[Extensions] Checking extension: {} (enabled: {})",
-            ext.id, ext.enabled
-        );
+        debug!("Checking extension: {} (enabled: {})", ext.id, ext.enabled);
 
         // Skip disabled extensions
         if !ext.enabled {
-            println!("[Extensions] Skipping disabled extension: {}", ext.id);
+            info!("Skipping disabled extension: {}", ext.id);
             continue;
         }
 
@@ -387,14 +470,14 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
         let settings = load_extension_settings(&ext.id);
 
         // Check if extension has MCP server config
-        println!(
-            "[Extensions] Extension {} has server: {}",
+        debug!(
+            "Extension {} has server: {}",
             ext.id,
             ext.manifest.server.is_some()
         );
         if let Some(server) = &ext.manifest.server {
-            println!(
-                "[Extensions] Extension {} has mcp_config: {}",
+            debug!(
+                "Extension {} has mcp_config: {}",
                 ext.id,
                 server.mcp_config.is_some()
             );
@@ -405,7 +488,8 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
 
                 for arg in &mcp_config.args {
                     // First resolve ${__dirname}
-                    let arg_with_dirname = arg.replace("${__dirname}", &ext.path);
+                    let arg_with_dirname =
+                        arg.replace("${__dirname}", &escape_expansion(&ext.path));
 
                     // Then resolve ${user_config.xxx}
                     if arg_with_dirname.contains("${user_config.") {
@@ -420,16 +504,12 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
                                 .nth(1)
                                 .and_then(|s| s.split('}').next())
                                 .unwrap_or("");
-                            if let Some(user_config_def) = &ext.manifest.user_config {
-                                if let Some(field_def) = user_config_def.get(key) {
-                                    if field_def.required.unwrap_or(false) {
-                                        has_unresolved_required = true;
-                                        println!(
-                                            "[Extensions] Extension {} has unresolved required user_config: {}",
-                                            ext.id, key
-                                        );
-                                    }
-                                }
+                            if is_required_user_config(&ext, key) {
+                                has_unresolved_required = true;
+                                warn!(
+                                    "Extension {} has unresolved required user_config: {}",
+                                    ext.id, key
+                                );
                             }
                         } else {
                             resolved_args.extend(expanded);
@@ -439,10 +519,43 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
                     }
                 }
 
+                // Env values and the command take the same placeholders as args
+                let command = match resolve_value_placeholders(&mcp_config.command, &ext, &settings)
+                {
+                    Ok(command) => command,
+                    Err(key) => {
+                        warn!(
+                            "Skipping extension {}: command uses unset user_config {}",
+                            ext.id, key
+                        );
+                        continue;
+                    }
+                };
+                let mut env = HashMap::new();
+                for (name, value) in &mcp_config.env {
+                    match resolve_value_placeholders(value, &ext, &settings) {
+                        Ok(value) => {
+                            env.insert(name.clone(), value);
+                        }
+                        Err(key) if is_required_user_config(&ext, &key) => {
+                            has_unresolved_required = true;
+                            warn!(
+                                "Extension {} has unresolved required user_config: {}",
+                                ext.id, key
+                            );
+                        }
+                        // Optional and unset: leave the variable out
+                        Err(key) => debug!(
+                            "Extension {}: {} not set, user_config {} is empty",
+                            ext.id, name, key
+                        ),
+                    }
+                }
+
                 // Skip extension if required user_config is missing
                 if has_unresolved_required {
-                    println!(
-                        "[Extensions] Skipping extension {} due to missing required user_config",
+                    warn!(
+                        "Skipping extension {} due to missing required user_config",
                         ext.id
                     );
                     continue;
@@ -455,9 +568,13 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
                         .display_name
                         .clone()
                         .unwrap_or(ext.manifest.name.clone()),
-                    command: mcp_config.command.clone(),
+                    command,
                     args: resolved_args,
-                    env: mcp_config.env.clone(),
+                    env,
+                    sandbox: settings
+                        .sandbox
+                        .clone()
+                        .map(|sandbox| extension_sandbox(sandbox, &ext, &settings)),
                 });
             }
         }
@@ -466,13 +583,45 @@ pub async fn extension_get_mcp_servers() -> Result<Vec<ExtensionMcpServer>, Stri
     Ok(mcp_servers)
 }
 
+/// Adds the extension directory (readable) and the paths chosen for
+/// `directory` (writable) and `file` (readable) user_config fields. Paths are
+/// escaped like substituted args, since sandbox paths are expanded at spawn time.
+fn extension_sandbox(
+    mut sandbox: SandboxConfig,
+    ext: &InstalledExtension,
+    settings: &ExtensionSettings,
+) -> SandboxConfig {
+    sandbox.read.push(escape_expansion(&ext.path));
+
+    let fields = ext.manifest.user_config.iter().flatten();
+    for (key, field) in fields {
+        let Some(value) = settings.user_config.get(key).or(field.default.as_ref()) else {
+            continue;
+        };
+        let paths: Vec<String> = match value {
+            serde_json::Value::String(path) => vec![escape_expansion(path)],
+            serde_json::Value::Array(items) => items
+                .iter()
+                .filter_map(|v| v.as_str().map(escape_expansion))
+                .collect(),
+            _ => continue,
+        };
+        match field.field_type.as_str() {
+            "directory" => sandbox.write.extend(paths),
+            "file" => sandbox.read.extend(paths),
+            _ => {}
+        }
+    }
+    sandbox
+}
+
 /// Set user config for an extension
 #[command]
 pub async fn extension_set_user_config(
     extension_id: String,
     key: String,
     value: serde_json::Value,
-) -> Result<(), String> {
+) -> CommandResult<()> {
     let settings_dir = get_extension_settings_dir();
     fs::create_dir_all(&settings_dir).ok();
 
@@ -485,11 +634,26 @@ pub async fn extension_set_user_config(
     settings.user_config.insert(key, value);
 
     // Save settings
-    fs::write(
-        &settings_path,
-        serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?,
-    )
-    .map_err(|e| format!("Failed to save settings: {}", e))?;
+    fs::write(&settings_path, serde_json::to_string_pretty(&settings)?)?;
+
+    Ok(())
+}
+
+/// Set or clear (`None`) the sandbox for an extension's MCP server
+#[command]
+pub async fn extension_set_sandbox(
+    extension_id: String,
+    sandbox: Option<SandboxConfig>,
+) -> CommandResult<()> {
+    let settings_dir = get_extension_settings_dir();
+    fs::create_dir_all(&settings_dir).ok();
+
+    let settings_path = settings_dir.join(format!("{}.json", extension_id));
+
+    let mut settings = load_extension_settings(&extension_id);
+    settings.sandbox = sandbox;
+
+    fs::write(&settings_path, serde_json::to_string_pretty(&settings)?)?;
 
     Ok(())
 }
@@ -498,19 +662,125 @@ pub async fn extension_set_user_config(
 #[command]
 pub async fn extension_get_user_config(
     extension_id: String,
-) -> Result<HashMap<String, serde_json::Value>, String> {
+) -> CommandResult<HashMap<String, serde_json::Value>> {
     let settings = load_extension_settings(&extension_id);
     Ok(settings.user_config)
 }
 
 /// Get extension manifest (including user_config definitions)
 #[command]
-pub async fn extension_get_manifest(extension_id: String) -> Result<ExtensionManifest, String> {
+pub async fn extension_get_manifest(extension_id: String) -> CommandResult<ExtensionManifest> {
     let extensions_dir = get_extensions_dir();
     let manifest_path = extensions_dir.join(&extension_id).join("manifest.json");
 
-    let content = fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    if !manifest_path.exists() {
+        return Err(ExtensionError::NotFound(extension_id).into());
+    }
+    let content = fs::read_to_string(&manifest_path)?;
+
+    let manifest = serde_json::from_str(&content).map_err(|e| ExtensionError::InvalidManifest {
+        id: extension_id,
+        reason: e.to_string(),
+    })?;
+    Ok(manifest)
+}
 
-    serde_json::from_str(&content).map_err(|e| format!("Failed to parse manifest: {}", e))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_env_placeholders() {
+        let ext: InstalledExtension = serde_json::from_value(serde_json::json!({
+            "id": "weather",
+            "path": "/ext/weather",
+            "enabled": true,
+            "manifest": {
+                "name": "weather",
+                "version": "1.0.0",
+                "server": {
+                    "type": "node",
+                    "entry_point": "server/index.js",
+                    "mcp_config": {
+                        "command": "${__dirname}/bin/node",
+                        "env": {"API_KEY": "${user_config.api_key}"}
+                    }
+                },
+                "user_config": {
+                    "api_key": {"type": "string", "required": true},
+                    "units": {"type": "string", "default": "metric"},
+                    "region": {"type": "string"}
+                }
+            }
+        }))
+        .unwrap();
+        let mut settings: ExtensionSettings = serde_json::from_value(serde_json::json!({
+            "user_config": {"api_key": "sk-1", "dirs": ["/a", "/b"]}
+        }))
+        .unwrap();
+
+        let resolve = |value: &str, settings: &ExtensionSettings| {
+            resolve_value_placeholders(value, &ext, settings)
+        };
+        assert_eq!(
+            resolve("${__dirname}/bin/node", &settings).unwrap(),
+            "/ext/weather/bin/node"
+        );
+        assert_eq!(
+            resolve("${user_config.api_key}", &settings).unwrap(),
+            "sk-1"
+        );
+        assert_eq!(
+            resolve("${user_config.units}", &settings).unwrap(),
+            "metric"
+        );
+        assert_eq!(resolve("${user_config.dirs}", &settings).unwrap(), "/a,/b");
+        assert_eq!(
+            resolve("${user_config.region}", &settings),
+            Err("region".to_string())
+        );
+        assert!(!is_required_user_config(&ext, "region"));
+
+        // Values are escaped for the spawn-time expansion and not expanded again
+        settings.user_config.insert(
+            "api_key".to_string(),
+            serde_json::json!("${user_config.api_key}"),
+        );
+        assert_eq!(
+            resolve("${user_config.api_key}", &settings).unwrap(),
+            "$${user_config.api_key}"
+        );
+        settings.user_config.remove("api_key");
+        assert_eq!(
+            resolve("${user_config.api_key}", &settings),
+            Err("api_key".to_string())
+        );
+        assert!(is_required_user_config(&ext, "api_key"));
+    }
+
+    #[test]
+    fn test_extension_sandbox_escapes_paths() {
+        let ext: InstalledExtension = serde_json::from_value(serde_json::json!({
+            "id": "files",
+            "path": "/home/u/$work/files",
+            "enabled": true,
+            "manifest": {
+                "name": "files",
+                "version": "1.0.0",
+                "user_config": {
+                    "dirs": {"type": "directory", "multiple": true},
+                    "key": {"type": "file"}
+                }
+            }
+        }))
+        .unwrap();
+        let settings: ExtensionSettings = serde_json::from_value(serde_json::json!({
+            "user_config": {"dirs": ["/data/$x", "/tmp"], "key": "/keys/${HOME}"}
+        }))
+        .unwrap();
+
+        let sandbox = extension_sandbox(SandboxConfig::default(), &ext, &settings);
+        assert_eq!(sandbox.read, vec!["/home/u/$$work/files", "/keys/$${HOME}"]);
+        assert_eq!(sandbox.write, vec!["/data/$$x", "/tmp"]);
+    }
 }
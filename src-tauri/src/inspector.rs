@@ -0,0 +1,48 @@
+//! MCP inspector window.
+//!
+//! A local page (`dist/inspector.html`) for debugging MCP servers: it lists
+//! servers, tools and resources, calls tools and reads resources through the
+//! `mcp_*` commands, and shows the traffic and stderr output of each server
+//! from the `mcp-traffic` and `mcp-stderr` events.
+
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tracing::info;
+
+use crate::error::{AppError, AppResult, CommandResult};
+use crate::mcp::McpManager;
+
+pub const WINDOW_LABEL: &str = "inspector";
+
+/// Opens the inspector, or focuses it if it is already open.
+///
+/// Async because creating a window from a synchronous command deadlocks on Windows.
+#[tauri::command]
+pub async fn mcp_open_inspector(app: AppHandle) -> CommandResult<()> {
+    Ok(open(&app)?)
+}
+
+pub fn open(app: &AppHandle) -> AppResult<()> {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    info!("Opening MCP inspector");
+    WebviewWindowBuilder::new(app, WINDOW_LABEL, WebviewUrl::App("inspector.html".into()))
+        .title("MCP Inspector")
+        .inner_size(1100.0, 760.0)
+        .build()
+        .map_err(|e| AppError::Other(format!("Failed to open the inspector: {}", e)))?;
+    Ok(())
+}
+
+/// Forwards server traffic and stderr output to the inspector while it is open.
+pub fn forward_events(manager: &McpManager, app: AppHandle) {
+    manager.set_monitor_handler(Arc::new(move |event| {
+        if app.get_webview_window(WINDOW_LABEL).is_some() {
+            let _ = app.emit_to(WINDOW_LABEL, event.name(), event);
+        }
+    }));
+}
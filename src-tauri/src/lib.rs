@@ -1,14 +1,20 @@
+pub mod cli;
 mod error;
 mod extensions;
+mod inspector;
+mod logging;
 mod mcp;
 mod webview;
 
+use error::{AppError, CommandResult};
 use mcp::McpManager;
 use std::sync::Arc;
 use tauri::webview::{DownloadEvent, NewWindowResponse, PageLoadEvent};
-use tauri::{Manager, WebviewWindowBuilder};
+use tauri::{Emitter, Manager, WebviewWindowBuilder};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use tauri_plugin_opener::OpenerExt;
 use tokio::sync::RwLock;
+use tracing::{debug, info};
 
 /// HTTP 代理請求 - 繞過 CSP 限制
 #[tauri::command]
@@ -17,7 +23,7 @@ async fn http_proxy_request(
     method: String,
     headers: std::collections::HashMap<String, String>,
     body: Option<String>,
-) -> Result<serde_json::Value, String> {
+) -> CommandResult<serde_json::Value> {
     use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
     let client = reqwest::Client::new();
@@ -52,7 +58,7 @@ async fn http_proxy_request(
     let response = request_builder
         .send()
         .await
-        .map_err(|e| format!("HTTP request failed: {}", e))?;
+        .map_err(|e| AppError::Http(format!("HTTP request failed: {}", e)))?;
 
     let status = response.status().as_u16();
     let status_text = response.status().canonical_reason().unwrap_or("").to_string();
@@ -67,7 +73,7 @@ async fn http_proxy_request(
     let body_bytes = response
         .bytes()
         .await
-        .map_err(|e| format!("Failed to read response body: {}", e))?;
+        .map_err(|e| AppError::Http(format!("Failed to read response body: {}", e)))?;
 
     let body_str = String::from_utf8_lossy(&body_bytes).to_string();
 
@@ -81,7 +87,7 @@ async fn http_proxy_request(
 
 /// Read file content as base64 for drag-drop upload
 #[tauri::command]
-async fn read_file_base64(path: String) -> Result<(String, String), String> {
+async fn read_file_base64(path: String) -> CommandResult<(String, String)> {
     use std::fs;
     use std::path::Path;
 
@@ -92,7 +98,7 @@ async fn read_file_base64(path: String) -> Result<(String, String), String> {
         .unwrap_or("file")
         .to_string();
 
-    let contents = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let contents = fs::read(path)?;
     let base64 = base64_encode(&contents);
 
     Ok((file_name, base64))
@@ -196,19 +202,26 @@ fn show_save_dialog(file_name: &str, default_dir: &str) -> Option<String> {
     }
 
     // 都失敗時，使用預設路徑
-    eprintln!("[Download] No dialog available, using default path: {}", default_path);
+    info!("No dialog available, using default path: {}", default_path);
     Some(default_path)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 記錄輸出到 stderr 與 app data 目錄中每日輪替的記錄檔
+    logging::init();
+
+    // 在背景解析登入 shell 的環境變數（PATH 等），供 MCP 伺服器啟動使用
+    mcp::shell_env::init();
+
     let mcp_manager = Arc::new(RwLock::new(McpManager::new()));
+    let mcp_manager_for_setup = mcp_manager.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             // 當有第二個實例嘗試啟動時，聚焦現有視窗
-            eprintln!("[Single Instance] Another instance detected, focusing existing window");
+            info!("Another instance detected, focusing existing window");
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_focus();
                 let _ = window.unminimize();
@@ -223,11 +236,26 @@ pub fn run() {
             mcp::mcp_load_servers,
             mcp::mcp_list_servers,
             mcp::mcp_call_tool,
+            mcp::mcp_query_audit_log,
             mcp::mcp_read_resource,
+            mcp::mcp_read_cached_result,
             mcp::mcp_stop_server,
+            mcp::mcp_get_stats,
             mcp::mcp_get_config,
             mcp::mcp_save_config,
             mcp::mcp_get_config_path,
+            mcp::mcp_list_workspaces,
+            mcp::mcp_add_workspace,
+            mcp::mcp_remove_workspace,
+            mcp::mcp_set_workspace_enabled,
+            mcp::mcp_set_project_server_trust,
+            mcp::mcp_list_profiles,
+            mcp::mcp_save_profile,
+            mcp::mcp_delete_profile,
+            mcp::mcp_switch_profile,
+            mcp::mcp_get_server_settings,
+            mcp::mcp_set_tool_permission,
+            inspector::mcp_open_inspector,
             extensions::extension_install,
             extensions::extension_list,
             extensions::extension_delete,
@@ -236,11 +264,44 @@ pub fn run() {
             extensions::extension_get_mcp_servers,
             extensions::extension_set_user_config,
             extensions::extension_get_user_config,
+            extensions::extension_set_sandbox,
             extensions::extension_get_manifest,
             read_file_base64,
             http_proxy_request,
         ])
-        .setup(|app| {
+        .setup(move |app| {
+            // 啟動未受信任的專案 MCP 伺服器前，以原生對話框詢問使用者
+            let dialog_handle = app.handle().clone();
+            if let Ok(manager) = mcp_manager_for_setup.try_read() {
+                manager.set_confirm_handler(Arc::new(move |title, message| {
+                    dialog_handle
+                        .dialog()
+                        .message(message)
+                        .title(title)
+                        .kind(MessageDialogKind::Warning)
+                        .buttons(MessageDialogButtons::OkCancelCustom(
+                            "Allow".to_string(),
+                            "Deny".to_string(),
+                        ))
+                        .blocking_show()
+                }));
+                // 背景啟動的伺服器就緒後通知前端，以最新的工具清單取代快取
+                let event_handle = app.handle().clone();
+                manager.set_change_handler(Arc::new(move || {
+                    let _ = event_handle.emit("mcp-servers-changed", ());
+                }));
+                // MCP Inspector 視窗開啟時，轉送各伺服器的 JSON-RPC 流量與 stderr
+                inspector::forward_events(&manager, app.handle().clone());
+                // 停止閒置超過 idleTimeoutMinutes 的伺服器
+                manager.start_idle_monitor();
+                // 定期 ping 設定了 healthCheck 的伺服器
+                manager.start_health_monitor();
+                // 依 stats.snapshotIntervalSeconds 定期寫入統計快照
+                manager.start_stats_snapshots();
+            }
+            // gateway.enabled 時，讓其他 MCP 客戶端透過本機 socket 共用伺服器
+            mcp::gateway::start(mcp_manager_for_setup.clone());
+
             // 從設定檔取得視窗設定並手動建立視窗
             let window_config = app.config().app.windows.first().cloned();
 
@@ -260,9 +321,9 @@ pub fn run() {
             // 外部連結處理：在預設瀏覽器中開啟
             let handle_for_new_window = app.handle().clone();
             builder = builder.on_new_window(move |url, _features| {
-                eprintln!("[WebView] on_new_window: {}", url);
+                debug!("on_new_window: {}", url);
                 if is_external_url(&url) {
-                    eprintln!("[WebView] Opening external URL in browser: {}", url);
+                    info!("Opening external URL in browser: {}", url);
                     let _ = handle_for_new_window.opener().open_url(url.as_str(), None::<&str>);
                     NewWindowResponse::Deny
                 } else {
@@ -275,7 +336,7 @@ pub fn run() {
             builder = builder.on_download(move |_webview, event| {
                 match event {
                     DownloadEvent::Requested { url, destination } => {
-                        eprintln!("[Download] Requested: {} -> {:?}", url, destination);
+                        info!("Requested: {} -> {:?}", url, destination);
 
                         // 從 URL query parameter 取得檔案名稱（claude.ai 使用 path= 參數）
                         let file_name = url
@@ -301,7 +362,7 @@ pub fn run() {
                             })
                             .unwrap_or_else(|| "download".to_string());
 
-                        eprintln!("[Download] File name: {}", file_name);
+                        debug!("File name: {}", file_name);
 
                         // 取得預設下載目錄
                         let default_dir = destination
@@ -317,19 +378,16 @@ pub fn run() {
                         let save_path = show_save_dialog(&file_name, &default_dir);
 
                         if let Some(path) = save_path {
-                            eprintln!("[Download] User selected: {:?}", path);
+                            info!("User selected: {:?}", path);
                             *destination = std::path::PathBuf::from(path);
                             true // 允許下載
                         } else {
-                            eprintln!("[Download] User cancelled");
+                            info!("User cancelled");
                             false // 取消下載
                         }
                     }
                     DownloadEvent::Finished { url, path, success } => {
-                        eprintln!(
-                            "[Download] Finished: {} -> {:?}, success: {}",
-                            url, path, success
-                        );
+                        info!("Finished: {} -> {:?}, success: {}", url, path, success);
                         true
                     }
                     _ => true,
@@ -340,7 +398,7 @@ pub fn run() {
             let handle_for_nav = app.handle().clone();
             builder = builder.on_navigation(move |url| {
                 if is_external_url(&url) {
-                    eprintln!("[WebView] Navigation to external URL, opening in browser: {}", url);
+                    info!("Navigation to external URL, opening in browser: {}", url);
                     let _ = handle_for_nav.opener().open_url(url.as_str(), None::<&str>);
                     false // 阻止 WebView 導航
                 } else {
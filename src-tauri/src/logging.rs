@@ -0,0 +1,91 @@
+//! Backend logging.
+//!
+//! Records go to stderr and to a daily rotated file in the app data directory
+//! (`~/.local/share/Claude/logs` on Linux), so logs are kept when the app is
+//! launched from the desktop. Levels and per-module filters are taken from
+//! `RUST_LOG`, or from `logging.level` in the config file.
+//!
+//! The command line interface only logs warnings and errors to stderr, so
+//! its JSON output on stdout stays clean.
+
+use std::path::PathBuf;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::mcp::config::McpConfig;
+
+const LOG_FILE_PREFIX: &str = "claude-desktop";
+
+pub fn log_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("Claude")
+        .join("logs")
+}
+
+/// Installs the global subscriber. Call once, before anything logs.
+pub fn init() {
+    let config = McpConfig::load().map(|c| c.logging).unwrap_or_default();
+
+    let (filter, invalid_level) = match EnvFilter::try_from_default_env() {
+        Ok(filter) => (filter, None),
+        Err(_) => match EnvFilter::try_new(&config.level) {
+            Ok(filter) => (filter, None),
+            Err(e) => (EnvFilter::new("info"), Some(e)),
+        },
+    };
+
+    // The appender writes synchronously: a background writer would lose the
+    // last records, since the process exits without dropping its guard
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(config.max_files.max(1))
+        .build(log_dir());
+    let (file_layer, file_error) = match file {
+        Ok(appender) => (
+            Some(fmt::layer().with_writer(appender).with_ansi(false)),
+            None,
+        ),
+        Err(e) => (None, Some(e)),
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .try_init()
+        .is_ok();
+    if !installed {
+        return;
+    }
+
+    if let Some(e) = invalid_level {
+        tracing::warn!(
+            "Invalid logging.level '{}', using 'info': {}",
+            config.level,
+            e
+        );
+    }
+    if let Some(e) = file_error {
+        tracing::warn!("Could not open log file in {}: {}", log_dir().display(), e);
+    }
+
+    // Release builds abort on panic; record the message before that happens
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        default_hook(info);
+    }));
+}
+
+/// Installs the subscriber for the command line interface.
+pub fn init_cli() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init();
+}
@@ -1,5 +1,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `claude-desktop mcp|ext|config ...` runs headless and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(status) = claude_desktop::cli::run(&args) {
+        std::process::exit(status);
+    }
     claude_desktop::run()
 }
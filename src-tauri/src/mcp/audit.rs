@@ -0,0 +1,385 @@
+//! Append-only audit log of MCP tool calls.
+//!
+//! Each call is written as one JSON line to `mcp-audit.jsonl` next to the
+//! config file. The file is rotated to `mcp-audit.jsonl.1`, `.2`, ... when it
+//! exceeds `audit.maxFileSize`, keeping `audit.maxFiles` rotated files.
+//!
+//! Arguments are stored either with sensitive fields redacted or as a hash
+//! only (`audit.arguments`). A hash of the full arguments is always recorded
+//! so identical calls can be matched without keeping their contents.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::config::{AuditArguments, AuditConfig, McpConfig};
+use crate::error::AppResult;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Field names that are always redacted (case-insensitive substring match)
+const DEFAULT_REDACT_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "authorization",
+    "cookie",
+    "credential",
+    "private_key",
+];
+
+/// Serializes appends and rotation within the process
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The server returned a result
+    Success,
+    /// The server returned a result with `isError: true`
+    ToolError,
+    /// The call did not produce a result (denied, rejected, timeout, ...)
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub server: String,
+    pub tool: String,
+    pub args_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Value>,
+    pub duration_ms: u64,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Size of the serialized result in bytes
+    #[serde(default)]
+    pub result_size: usize,
+}
+
+/// Filter for [`query`]. Times are Unix milliseconds, inclusive.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub server: Option<String>,
+    pub tool: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        if matches!(&self.server, Some(s) if *s != entry.server) {
+            return false;
+        }
+        if matches!(&self.tool, Some(t) if *t != entry.tool) {
+            return false;
+        }
+        if matches!(self.since, Some(since) if entry.timestamp < since) {
+            return false;
+        }
+        !matches!(self.until, Some(until) if entry.timestamp > until)
+    }
+}
+
+pub fn log_path() -> PathBuf {
+    McpConfig::config_path()
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mcp-audit.jsonl")
+}
+
+/// Builds an entry for a finished tool call.
+pub fn entry(
+    config: &AuditConfig,
+    server: &str,
+    tool: &str,
+    arguments: &Value,
+    duration: Duration,
+    result: &AppResult<Value>,
+) -> AuditEntry {
+    let (outcome, error, result_size) = match result {
+        Ok(value) => {
+            let outcome = if value.get("isError").and_then(|v| v.as_bool()) == Some(true) {
+                AuditOutcome::ToolError
+            } else {
+                AuditOutcome::Success
+            };
+            let size = serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0);
+            (outcome, None, size)
+        }
+        Err(e) => (AuditOutcome::Failed, Some(e.to_string()), 0),
+    };
+
+    AuditEntry {
+        timestamp: now_millis(),
+        server: server.to_string(),
+        tool: tool.to_string(),
+        args_hash: hash_value(arguments),
+        args: match config.arguments {
+            AuditArguments::Redacted => Some(redact(arguments, &config.redact_keys)),
+            AuditArguments::Hash => None,
+        },
+        duration_ms: duration.as_millis() as u64,
+        outcome,
+        error,
+        result_size,
+    }
+}
+
+/// Appends an entry to the audit log, rotating first if needed.
+pub fn record(config: &AuditConfig, entry: &AuditEntry) {
+    if !config.enabled {
+        return;
+    }
+    if let Err(e) = append(&log_path(), config, entry) {
+        tracing::warn!("Failed to write audit log: {}", e);
+    }
+}
+
+fn append(path: &Path, config: &AuditConfig, entry: &AuditEntry) -> Result<(), String> {
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+    line.push('\n');
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create log directory: {}", e))?;
+    }
+
+    let current = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if current > 0 && current + line.len() as u64 > config.max_file_size {
+        rotate(path, config.max_files);
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// `log` -> `log.1` -> `log.2` ...; the oldest file beyond `max_files` is dropped.
+fn rotate(path: &Path, max_files: usize) {
+    if max_files == 0 {
+        let _ = std::fs::remove_file(path);
+        return;
+    }
+    let _ = std::fs::remove_file(rotated_path(path, max_files));
+    for index in (1..max_files).rev() {
+        let _ = std::fs::rename(rotated_path(path, index), rotated_path(path, index + 1));
+    }
+    let _ = std::fs::rename(path, rotated_path(path, 1));
+}
+
+/// Returns matching entries, newest first.
+pub fn query(filter: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    let max_files = McpConfig::load().map(|c| c.audit.max_files).unwrap_or(5);
+    query_files(&log_path(), max_files, filter)
+}
+
+fn query_files(
+    path: &Path,
+    max_files: usize,
+    filter: &AuditQuery,
+) -> Result<Vec<AuditEntry>, String> {
+    let limit = filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    let mut results = Vec::new();
+
+    let files =
+        std::iter::once(path.to_path_buf()).chain((1..=max_files).map(|i| rotated_path(path, i)));
+
+    for file in files {
+        if results.len() >= limit {
+            break;
+        }
+        let handle = match std::fs::File::open(&file) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to read {}: {}", file.display(), e)),
+        };
+
+        // Each file is in chronological order; walk it backwards
+        let entries: Vec<AuditEntry> = BufReader::new(handle)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        results.extend(
+            entries
+                .into_iter()
+                .rev()
+                .filter(|e| filter.matches(e))
+                .take(limit - results.len()),
+        );
+    }
+
+    Ok(results)
+}
+
+/// Replaces values of sensitive fields, recursively.
+pub fn redact(value: &Value, extra_keys: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, v)| {
+                    if is_sensitive_key(key, extra_keys) {
+                        (key.clone(), Value::String(REDACTED.to_string()))
+                    } else {
+                        (key.clone(), redact(v, extra_keys))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact(v, extra_keys)).collect()),
+        other => other.clone(),
+    }
+}
+
+fn is_sensitive_key(key: &str, extra_keys: &[String]) -> bool {
+    let key = key.to_lowercase();
+    DEFAULT_REDACT_KEYS.iter().any(|k| key.contains(k))
+        || extra_keys
+            .iter()
+            .any(|k| !k.is_empty() && key.contains(&k.to_lowercase()))
+}
+
+fn hash_value(value: &Value) -> String {
+    let digest = Sha256::digest(value.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact() {
+        let args = json!({
+            "path": "/tmp",
+            "apiKey": "abc",
+            "headers": [{"Authorization": "Bearer x", "accept": "json"}],
+            "db": {"connection": "postgres://", "user_password": "pw"}
+        });
+        let redacted = redact(&args, &["connection".to_string()]);
+        assert_eq!(redacted["path"], "/tmp");
+        assert_eq!(redacted["apiKey"], REDACTED);
+        assert_eq!(redacted["headers"][0]["Authorization"], REDACTED);
+        assert_eq!(redacted["headers"][0]["accept"], "json");
+        assert_eq!(redacted["db"]["connection"], REDACTED);
+        assert_eq!(redacted["db"]["user_password"], REDACTED);
+    }
+
+    #[test]
+    fn test_entry_outcome() {
+        let config = AuditConfig {
+            arguments: AuditArguments::Hash,
+            ..Default::default()
+        };
+        let args = json!({"q": 1});
+        let ok = entry(
+            &config,
+            "s",
+            "t",
+            &args,
+            Duration::from_millis(5),
+            &Ok(json!({"isError": true})),
+        );
+        assert_eq!(ok.outcome, AuditOutcome::ToolError);
+        assert!(ok.args.is_none());
+        assert_eq!(ok.args_hash, hash_value(&args));
+
+        let failed = entry(
+            &config,
+            "s",
+            "t",
+            &args,
+            Duration::ZERO,
+            &Err("denied".into()),
+        );
+        assert_eq!(failed.outcome, AuditOutcome::Failed);
+        assert_eq!(failed.error.as_deref(), Some("denied"));
+    }
+
+    #[test]
+    fn test_rotation_and_query() {
+        let dir = std::env::temp_dir().join(format!("mcp-audit-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("audit.jsonl");
+        let config = AuditConfig {
+            max_file_size: 400,
+            max_files: 2,
+            ..Default::default()
+        };
+
+        for i in 0..12u64 {
+            let mut e = entry(
+                &config,
+                if i % 2 == 0 { "even" } else { "odd" },
+                "tool",
+                &json!({"i": i}),
+                Duration::ZERO,
+                &Ok(json!({})),
+            );
+            e.timestamp = i;
+            append(&path, &config, &e).unwrap();
+        }
+
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        let all = query_files(&path, 2, &AuditQuery::default()).unwrap();
+        assert_eq!(all[0].timestamp, 11);
+        assert!(all.windows(2).all(|w| w[0].timestamp > w[1].timestamp));
+
+        let filtered = query_files(
+            &path,
+            2,
+            &AuditQuery {
+                server: Some("even".to_string()),
+                since: Some(6),
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let timestamps: Vec<u64> = filtered.iter().map(|e| e.timestamp).collect();
+        assert_eq!(timestamps, vec![10, 8]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,178 @@
+//! Last known tool and resource lists per server (`mcp-catalog.json`).
+//!
+//! Each entry is keyed by a hash of the command, arguments and working
+//! directory it was listed with, so a changed server definition never shows
+//! an old catalog. The server version reported at `initialize` is kept with
+//! it.
+//!
+//! Cached catalogs are listed (marked stale) for lazy and idle-stopped
+//! servers, and for servers that are still starting in the background.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::client::{McpResource, McpTool};
+use super::config::{McpConfig, McpServerConfig};
+
+/// Serializes read-modify-write of the catalog file
+static CATALOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerCatalog {
+    /// `catalog_key` of the config the catalog was listed with
+    #[serde(default)]
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_version: Option<String>,
+    /// Unix time in milliseconds
+    #[serde(default)]
+    pub updated_at: u64,
+    #[serde(default)]
+    pub tools: Vec<McpTool>,
+    #[serde(default)]
+    pub resources: Vec<McpResource>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(flatten)]
+    servers: HashMap<String, ServerCatalog>,
+}
+
+impl Catalog {
+    pub fn path() -> PathBuf {
+        McpConfig::config_path()
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("mcp-catalog.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize catalog: {}", e))?;
+        std::fs::write(&path, content).map_err(|e| format!("Failed to write catalog: {}", e))
+    }
+
+    /// The cached catalog, if it was listed with the same command line.
+    pub fn get(&self, server: &str, config: &McpServerConfig) -> Option<&ServerCatalog> {
+        self.servers
+            .get(server)
+            .filter(|c| c.key == catalog_key(config))
+    }
+
+    fn insert(&mut self, server: &str, catalog: ServerCatalog) {
+        self.servers.insert(server.to_string(), catalog);
+    }
+}
+
+/// Hash of the command line a catalog belongs to.
+///
+/// The env is left out: it rarely changes the tool list and often holds
+/// rotating secrets.
+pub fn catalog_key(config: &McpServerConfig) -> String {
+    let canonical = serde_json::json!({
+        "command": config.command,
+        "args": config.args,
+        "cwd": config.cwd,
+    });
+    let digest = Sha256::digest(canonical.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Records the catalog of a server that just started.
+pub fn update(
+    server: &str,
+    config: &McpServerConfig,
+    server_version: Option<&str>,
+    tools: &[McpTool],
+    resources: &[McpResource],
+) {
+    let _guard = CATALOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut catalog = Catalog::load();
+    catalog.insert(
+        server,
+        ServerCatalog {
+            key: catalog_key(config),
+            server_version: server_version.map(str::to_string),
+            updated_at: now_millis(),
+            tools: tools.to_vec(),
+            resources: resources.to_vec(),
+        },
+    );
+    if let Err(e) = catalog.save() {
+        tracing::warn!("{}", e);
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(command: &str, args: &[&str]) -> McpServerConfig {
+        McpServerConfig {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lookup_requires_matching_key() {
+        let config = server("npx", &["-y", "@modelcontextprotocol/server-memory@1.0.0"]);
+        let mut catalog = Catalog::default();
+        catalog.insert(
+            "memory",
+            ServerCatalog {
+                key: catalog_key(&config),
+                server_version: Some("1.0.0".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(catalog.get("memory", &config).is_some());
+        assert!(catalog.get("other", &config).is_none());
+
+        // A different package version or command invalidates the entry
+        let upgraded = server("npx", &["-y", "@modelcontextprotocol/server-memory@1.1.0"]);
+        assert!(catalog.get("memory", &upgraded).is_none());
+
+        // Env changes keep the cached catalog
+        let mut with_env = config.clone();
+        with_env
+            .env
+            .insert("TOKEN".to_string(), "secret".to_string());
+        assert!(catalog.get("memory", &with_env).is_some());
+    }
+
+    #[test]
+    fn test_entries_without_key_are_ignored() {
+        let catalog: Catalog =
+            serde_json::from_str(r#"{"memory": {"tools": [], "resources": []}}"#).unwrap();
+        assert!(catalog.get("memory", &server("npx", &[])).is_none());
+    }
+}
@@ -2,23 +2,81 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, ChildStdin, Command, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::sync::oneshot;
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout, Duration, Instant};
+use tracing::{debug, info, trace, warn, Instrument};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
-use super::config::McpServerConfig;
+use super::config::{EnvInheritance, McpServerConfig};
+use super::expand::expand_config;
+use super::framing::{self, Frame, MessageReader};
+use super::jsonrpc::{self, Id, Inbound};
+use super::limits::{self, Limit};
+use super::monitor::Monitor;
+use super::queue::{QueueStats, RequestQueue};
+use super::recording::{Direction, Recorder};
+use super::sandbox;
+use super::schema::ToolSchemas;
+use super::shell_env;
+use crate::error::McpError;
+
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// In-flight requests waiting for a response, keyed by request id
+type PendingRequests = Arc<Mutex<HashMap<Id, oneshot::Sender<Result<Value, McpError>>>>>;
+
+/// Observers of the frames exchanged with a server
+#[derive(Clone)]
+struct Taps {
+    server: String,
+    /// Records the traffic of a server with `record` set
+    recorder: Option<Arc<Recorder>>,
+    monitor: Monitor,
+}
+
+impl Taps {
+    fn frame(&self, direction: Direction, message: &Value) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(direction, message);
+        }
+        self.monitor.traffic(&self.server, direction, message);
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpTool {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     pub description: Option<String>,
     #[serde(default, alias = "inputSchema")]
     pub input_schema: Value,
+    #[serde(default, alias = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Behavior hints a server declares for a tool (MCP `ToolAnnotations`).
+/// These are hints from the server, not guarantees.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,24 +88,111 @@ pub struct McpResource {
     pub mime_type: Option<String>,
 }
 
+/// Ping results used by the health check
+#[derive(Default)]
+struct Health {
+    checking: bool,
+    last_check: Option<Instant>,
+    latency: Option<Duration>,
+    failures: u32,
+}
+
 pub struct McpClient {
     #[allow(dead_code)]
     name: String,
     pub display_name: String,
-    process: Child,
+    /// Config the server was started with (before expansion)
+    pub config: McpServerConfig,
+    process: Mutex<Child>,
+    /// Limit the server's stderr output suggests it ran into
+    limit_hint: Arc<Mutex<Option<Limit>>>,
     stdin: Arc<Mutex<std::process::ChildStdin>>,
-    pending_requests: Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>,
+    taps: Taps,
+    pending_requests: PendingRequests,
     request_id: AtomicU64,
+    queue: RequestQueue,
+    last_used: Mutex<Instant>,
+    started_at: Instant,
+    health: Mutex<Health>,
+    /// `serverInfo.version` reported at initialize
+    pub server_version: Option<String>,
     pub tools: Vec<McpTool>,
     pub resources: Vec<McpResource>,
+    /// Compiled schemas of `tools`, by tool name
+    tool_schemas: HashMap<String, ToolSchemas>,
 }
 
 impl McpClient {
-    pub fn spawn(name: &str, display_name: &str, config: &McpServerConfig) -> Result<Self, String> {
-        let mut cmd = Command::new(&config.command);
-        cmd.args(&config.args)
-            .envs(&config.env)
-            .stdin(Stdio::piped())
+    pub fn spawn(
+        name: &str,
+        display_name: &str,
+        config: &McpServerConfig,
+        monitor: Monitor,
+    ) -> Result<Self, String> {
+        let original_config = config.clone();
+
+        // Resolve ~, ${HOME}, ${env:VAR} etc. before handing values to the process
+        let config = expand_config(config)
+            .map_err(|e| format!("Failed to spawn MCP server '{}': {}", name, e))?;
+
+        // GUI launches inherit a minimal PATH; search the login shell's PATH as well
+        let search_path = config
+            .env
+            .get("PATH")
+            .cloned()
+            .unwrap_or_else(shell_env::merged_path);
+        let program = shell_env::resolve_command(&config.command, &search_path)
+            .map_err(|e| format!("Failed to spawn MCP server '{}': {}", name, e))?;
+
+        // If the command was found in a fallback location (e.g. ~/.nvm/...), put that
+        // directory on PATH too so `#!/usr/bin/env node` style scripts work
+        let mut child_path = search_path.clone();
+        if let Some(dir) = program.parent().filter(|d| !d.as_os_str().is_empty()) {
+            if !std::env::split_paths(&search_path).any(|p| p == dir) {
+                let entries = std::iter::once(dir.to_path_buf())
+                    .chain(std::env::split_paths(&search_path));
+                if let Ok(joined) = std::env::join_paths(entries) {
+                    child_path = joined.to_string_lossy().to_string();
+                }
+            }
+        }
+
+        let mut cmd = Command::new(&program);
+        cmd.args(&config.args);
+
+        let pass_path = match &config.inherit_env {
+            EnvInheritance::All => true,
+            EnvInheritance::Allowlist(keys) => {
+                cmd.env_clear();
+                for key in keys {
+                    if let Some(value) = std::env::var_os(key) {
+                        cmd.env(key, value);
+                    }
+                }
+                keys.iter().any(|k| k == "PATH")
+            }
+            EnvInheritance::Clean => {
+                cmd.env_clear();
+                false
+            }
+        };
+
+        if let Some(cwd) = &config.cwd {
+            if !std::path::Path::new(cwd).is_dir() {
+                return Err(format!(
+                    "Failed to spawn MCP server '{}': working directory '{}' does not exist",
+                    name, cwd
+                ));
+            }
+            cmd.current_dir(cwd);
+        }
+
+        cmd.envs(&config.env);
+        if pass_path || config.env.contains_key("PATH") {
+            cmd.env("PATH", &child_path);
+        }
+
+        cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
@@ -58,9 +203,35 @@ impl McpClient {
             cmd.creation_flags(CREATE_NO_WINDOW);
         }
 
-        let mut process = cmd
-            .spawn()
-            .map_err(|e| format!("Failed to spawn MCP server '{}': {}", name, e))?;
+        if let Some(resource_limits) = &config.limits {
+            limits::apply(&mut cmd, resource_limits)
+                .map_err(|e| format!("Failed to spawn MCP server '{}': {}", name, e))?;
+        }
+
+        // Restrict file system and network access if a sandbox is configured
+        let sandboxed = match &config.sandbox {
+            Some(sandbox) => {
+                let paths =
+                    sandbox::allowed_paths(sandbox, &program, &child_path, config.cwd.as_deref());
+                Some(
+                    sandbox::prepare(&mut cmd, sandbox, &paths)
+                        .map_err(|e| format!("Failed to spawn MCP server '{}': {}", name, e))?,
+                )
+            }
+            None => None,
+        };
+
+        let mut process = cmd.spawn().map_err(|e| {
+            if sandboxed.is_some() {
+                format!(
+                    "Failed to spawn MCP server '{}': could not enter the sandbox: {}",
+                    name, e
+                )
+            } else {
+                format!("Failed to spawn MCP server '{}': {}", name, e)
+            }
+        })?;
+        drop(sandboxed);
 
         let stdin = process
             .stdin
@@ -77,123 +248,193 @@ impl McpClient {
             .take()
             .ok_or_else(|| format!("Failed to get stderr for '{}'", name))?;
 
-        let pending_requests: Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>> =
-            Arc::new(Mutex::new(HashMap::new()));
+        let pending_requests: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let stdin = Arc::new(Mutex::new(stdin));
+
+        let recorder = if config.record {
+            match Recorder::create(name, &original_config) {
+                Ok(recorder) => {
+                    info!(server = %name, "Recording traffic to {}", recorder.path.display());
+                    Some(Arc::new(recorder))
+                }
+                Err(e) => {
+                    warn!(server = %name, "Not recording traffic: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let taps = Taps {
+            server: name.to_string(),
+            recorder,
+            monitor,
+        };
+        let taps_clone = taps.clone();
 
         let pending_clone = pending_requests.clone();
+        let stdin_clone = stdin.clone();
         let name_clone = name.to_string();
 
-        // Spawn stdout reader thread with improved error handling
+        let max_message_size = config
+            .max_message_size
+            .unwrap_or(framing::DEFAULT_MAX_MESSAGE_SIZE);
+
+        // Spawn stdout reader thread. Messages are framed by JSON structure rather
+        // than by line, so multiline JSON and batches are handled too.
         std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line_result in reader.lines() {
-                match line_result {
-                    Ok(line) => {
-                        // Skip empty lines
-                        if line.trim().is_empty() {
-                            continue;
+            let mut reader = MessageReader::new(stdout, max_message_size);
+            loop {
+                let frame = reader.next_frame();
+                if let Ok(Some(Frame::Message(message))) = &frame {
+                    taps_clone.frame(Direction::In, message);
+                }
+                match frame {
+                    Ok(Some(Frame::Message(Value::Array(batch)))) => {
+                        if batch.is_empty() {
+                            warn!(server = %name_clone, "Ignoring empty batch");
                         }
-
-                        // Try to parse as JSON-RPC response
-                        match serde_json::from_str::<Value>(&line) {
-                            Ok(response) => {
-                                // Handle JSON-RPC response
-                                if let Some(id) = response.get("id").and_then(|v| v.as_u64()) {
-                                    if let Some(sender) = pending_clone.lock().unwrap().remove(&id)
-                                    {
-                                        if let Some(error) = response.get("error") {
-                                            eprintln!(
-                                                "[MCP] Received error response: id={}, error={:?}",
-                                                id, error
-                                            );
-                                            let _ = sender.send(Err(error.to_string()));
-                                        } else if let Some(result) = response.get("result") {
-                                            eprintln!(
-                                                "[MCP] Received success response: id={}, result_size={} bytes",
-                                                id,
-                                                serde_json::to_string(result).map(|s| s.len()).unwrap_or(0)
-                                            );
-                                            let _ = sender.send(Ok(result.clone()));
-                                        }
-                                    } else {
-                                        eprintln!(
-                                            "[MCP] Received response for unknown or already-completed request: id={}",
-                                            id
-                                        );
-                                    }
-                                } else {
-                                    // This might be a request from server to client (e.g., roots/list)
-                                    if let Some(method) =
-                                        response.get("method").and_then(|v| v.as_str())
-                                    {
-                                        eprintln!(
-                                            "[MCP] Received request from server '{}': method={}, treating as notification",
-                                            name_clone, method
-                                        );
-                                        // Server-to-client requests (like roots/list) should be handled
-                                        // but we currently don't support them - just log
-                                    } else {
-                                        // Notification or malformed response
-                                        eprintln!(
-                                            "[MCP] Received notification or response without id: {:?}",
-                                            response
-                                        );
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "[MCP] Failed to parse JSON response from '{}': {} | Line: {}",
-                                    name_clone, e, line
-                                );
-                                // Don't exit - continue reading next line
-                            }
+                        for message in &batch {
+                            handle_message(
+                                &name_clone,
+                                message,
+                                &pending_clone,
+                                &stdin_clone,
+                                &taps_clone,
+                            );
                         }
                     }
+                    Ok(Some(Frame::Message(message))) => {
+                        handle_message(
+                            &name_clone,
+                            &message,
+                            &pending_clone,
+                            &stdin_clone,
+                            &taps_clone,
+                        );
+                    }
+                    Ok(Some(Frame::Oversized { id, size })) => {
+                        fail_oversized(&name_clone, id, size, max_message_size, &pending_clone);
+                    }
+                    Ok(Some(Frame::Malformed(e))) => {
+                        warn!(server = %name_clone, "Failed to parse JSON response: {}", e);
+                        // Don't exit - continue reading next message
+                    }
+                    Ok(Some(Frame::Noise(line))) => {
+                        debug!(server = %name_clone, "Non-JSON output: {}", line);
+                    }
+                    Ok(None) => break,
                     Err(e) => {
-                        eprintln!("[MCP] Error reading line from '{}': {}", name_clone, e);
-                        // Don't exit immediately - the error might be temporary
-                        // But if it's EOF or broken pipe, the loop will end naturally
+                        warn!(server = %name_clone, "Error reading stdout: {}", e);
+                        break;
                     }
                 }
             }
-            eprintln!("[MCP] stdout reader thread exited for '{}'", name_clone);
+            debug!(server = %name_clone, "stdout reader thread exited");
         });
 
         // Spawn stderr reader thread to prevent blocking with improved error handling
         let name_clone = name.to_string();
+        let limit_hint = Arc::new(Mutex::new(None));
+        let limit_hint_clone = limit_hint.clone();
+        let resource_limits = config.limits.clone();
+        let monitor = taps.monitor.clone();
         std::thread::spawn(move || {
             let reader = BufReader::new(stderr);
             for line_result in reader.lines() {
                 match line_result {
                     Ok(line) => {
                         if !line.trim().is_empty() {
-                            eprintln!("[MCP stderr] {}: {}", name_clone, line);
+                            info!(target: "claude_desktop::mcp::stderr", server = %name_clone, "{}", line);
+                            monitor.stderr(&name_clone, &line);
+                        }
+                        if let Some(hint) = resource_limits
+                            .as_ref()
+                            .and_then(|l| limits::stderr_hint(&line, l))
+                        {
+                            *limit_hint_clone.lock().unwrap() = Some(hint);
                         }
                     }
                     Err(e) => {
-                        eprintln!("[MCP] Error reading stderr from '{}': {}", name_clone, e);
+                        warn!(server = %name_clone, "Error reading stderr: {}", e);
                     }
                 }
             }
-            eprintln!("[MCP] stderr reader thread exited for '{}'", name_clone);
+            debug!(server = %name_clone, "stderr reader thread exited");
         });
 
         Ok(Self {
             name: name.to_string(),
             display_name: display_name.to_string(),
-            process,
-            stdin: Arc::new(Mutex::new(stdin)),
+            config: original_config,
+            process: Mutex::new(process),
+            limit_hint,
+            stdin,
+            taps,
             pending_requests,
             request_id: AtomicU64::new(1),
+            queue: RequestQueue::new(config.max_in_flight, config.max_queued),
+            last_used: Mutex::new(Instant::now()),
+            started_at: Instant::now(),
+            health: Mutex::new(Health::default()),
+            server_version: None,
             tools: Vec::new(),
             resources: Vec::new(),
+            tool_schemas: HashMap::new(),
         })
     }
 
-    pub async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
-        let id = self.request_id.fetch_add(1, Ordering::SeqCst);
+    pub async fn send_request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        self.touch();
+
+        // Wait for a free slot if the server limits concurrent requests
+        let _slot = self.queue.acquire().await.map_err(|full| {
+            warn!(server = %self.name, method, "Request queue full");
+            McpError::from(full)
+        })?;
+
+        let result = self
+            .request(method, params, Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .await;
+        self.touch();
+        result
+    }
+
+    /// Sends an MCP `ping` and returns the round-trip time.
+    ///
+    /// Pings bypass the request queue and do not count as activity for
+    /// `idleTimeoutMinutes`. A JSON-RPC error reply (e.g. a server without
+    /// `ping` support) still shows the server is responsive.
+    pub async fn ping(&self, wait: Duration) -> Result<Duration, McpError> {
+        let started = Instant::now();
+        match self.request("ping", json!({}), wait).await {
+            Ok(_) | Err(McpError::JsonRpc { .. }) => Ok(started.elapsed()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes a request and waits for its response.
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        wait: Duration,
+    ) -> Result<Value, McpError> {
+        let id = Id::Number(self.request_id.fetch_add(1, Ordering::SeqCst) as i64);
+        // Ties the log records of this request to the surrounding tool call
+        let span = tracing::debug_span!("request", server = %self.name, id = %id, method);
+        self.send_and_wait(id, method, params, wait)
+            .instrument(span)
+            .await
+    }
 
+    async fn send_and_wait(
+        &self,
+        id: Id,
+        method: &str,
+        params: Value,
+        wait: Duration,
+    ) -> Result<Value, McpError> {
         let request = json!({
             "jsonrpc": "2.0",
             "id": id,
@@ -204,56 +445,44 @@ impl McpClient {
         let (tx, rx) = oneshot::channel();
         {
             let mut pending = self.pending_requests.lock().unwrap();
-            eprintln!("[MCP] Pending requests before insert: {}", pending.len());
-            pending.insert(id, tx);
-            eprintln!("[MCP] Pending requests after insert: {}", pending.len());
+            pending.insert(id.clone(), tx);
+            trace!(pending = pending.len(), "Request registered");
         }
 
-        let mut request_str = serde_json::to_string(&request)
-            .map_err(|e| format!("Failed to serialize request: {}", e))?;
-        request_str.push('\n');
-
-        eprintln!(
-            "[MCP] Sending request: id={}, method={}, server={}, params_size={} bytes",
-            id,
-            method,
-            self.name,
-            serde_json::to_string(&params).map(|s| s.len()).unwrap_or(0)
+        debug!(
+            params_size = serde_json::to_string(&params).map(|s| s.len()).unwrap_or(0),
+            "Sending request"
         );
 
-        // Critical: Write and flush in a separate scope to drop the lock before await
-        {
-            let mut stdin = self.stdin.lock().unwrap();
-            stdin
-                .write_all(request_str.as_bytes())
-                .map_err(|e| format!("Failed to write to stdin: {}", e))?;
-
-            // Flush to ensure the request is sent immediately
-            stdin
-                .flush()
-                .map_err(|e| format!("Failed to flush stdin: {}", e))?;
-        } // Lock is dropped here, before the await
+        // Write and flush without holding the lock across the await
+        if let Err(e) = write_message(&self.stdin, &self.taps, &request) {
+            self.pending_requests.lock().unwrap().remove(&id);
+            return Err(McpError::Communication(format!(
+                "Failed to write to stdin: {}",
+                e
+            )));
+        }
 
-        // Add 30-second timeout to prevent hanging requests
-        let result = timeout(Duration::from_secs(30), rx).await;
+        // Timeout to prevent hanging requests
+        let result = timeout(wait, rx).await;
 
         match result {
             Ok(Ok(response)) => {
-                eprintln!("[MCP] Request completed: id={}, method={}", id, method);
+                debug!("Request completed");
                 response
             }
             Ok(Err(_)) => {
-                eprintln!("[MCP] Request cancelled: id={}, method={}", id, method);
-                Err("Request cancelled".to_string())
+                warn!("Request cancelled");
+                Err(McpError::Cancelled)
             }
             Err(_) => {
                 // Timeout - remove from pending_requests to prevent memory leak
                 self.pending_requests.lock().unwrap().remove(&id);
-                eprintln!(
-                    "[MCP] Request timeout after 30s: id={}, method={}, server={}",
-                    id, method, self.name
-                );
-                Err(format!("Request timeout after 30s: {}", method))
+                warn!("Request timeout after {}s", wait.as_secs());
+                Err(McpError::Timeout {
+                    method: method.to_string(),
+                    timeout_secs: wait.as_secs(),
+                })
             }
         }
     }
@@ -261,16 +490,19 @@ impl McpClient {
     pub async fn initialize(&mut self) -> Result<(), String> {
         let params = json!({
             "protocolVersion": "2024-11-05",
-            "capabilities": {
-                "sampling": {}
-            },
+            // No client features (sampling, roots) are implemented
+            "capabilities": {},
             "clientInfo": {
                 "name": "claude-desktop-tauri",
                 "version": "0.1.0"
             }
         });
 
-        let _result = self.send_request("initialize", params).await?;
+        let result = self.send_request("initialize", params).await?;
+        self.server_version = result
+            .pointer("/serverInfo/version")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
 
         // Send initialized notification
         let notification = json!({
@@ -278,32 +510,29 @@ impl McpClient {
             "method": "notifications/initialized"
         });
 
-        let mut notif_str = serde_json::to_string(&notification)
-            .map_err(|e| format!("Failed to serialize notification: {}", e))?;
-        notif_str.push('\n');
-
-        {
-            let mut stdin = self.stdin.lock().unwrap();
-            stdin
-                .write_all(notif_str.as_bytes())
-                .map_err(|e| format!("Failed to send initialized notification: {}", e))?;
-            stdin
-                .flush()
-                .map_err(|e| format!("Failed to flush initialized notification: {}", e))?;
-        } // Drop lock before any potential await
+        write_message(&self.stdin, &self.taps, &notification)
+            .map_err(|e| format!("Failed to send initialized notification: {}", e))?;
 
         // List tools
         if let Ok(result) = self.send_request("tools/list", json!({})).await {
-            eprintln!("[MCP] tools/list response: {:?}", result);
+            trace!(server = %self.name, "tools/list response: {}", result);
             if let Some(tools) = result.get("tools").and_then(|v| v.as_array()) {
                 self.tools = tools
                     .iter()
-                    .filter_map(|t| {
-                        eprintln!("[MCP] Parsing tool: {:?}", t);
-                        serde_json::from_value(t.clone()).ok()
+                    .filter_map(|t| match serde_json::from_value(t.clone()) {
+                        Ok(tool) => Some(tool),
+                        Err(e) => {
+                            warn!(server = %self.name, "Ignoring invalid tool: {} | {}", e, t);
+                            None
+                        }
                     })
                     .collect();
-                eprintln!("[MCP] Parsed tools: {:?}", self.tools);
+                self.tool_schemas = self
+                    .tools
+                    .iter()
+                    .map(|t| (t.name.clone(), ToolSchemas::new(t)))
+                    .collect();
+                debug!(server = %self.name, tools = self.tools.len(), "Parsed tools");
             }
         }
 
@@ -320,7 +549,7 @@ impl McpClient {
         Ok(())
     }
 
-    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, String> {
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, McpError> {
         let params = json!({
             "name": name,
             "arguments": arguments
@@ -328,21 +557,236 @@ impl McpClient {
         self.send_request("tools/call", params).await
     }
 
-    pub async fn read_resource(&self, uri: &str) -> Result<Value, String> {
+    pub async fn read_resource(&self, uri: &str) -> Result<Value, McpError> {
         let params = json!({
             "uri": uri
         });
         self.send_request("resources/read", params).await
     }
 
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    /// Time since the last request started or finished
+    pub fn idle_time(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
+
+    /// Time since the process was spawned
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Compiled schemas of a tool, by its name on the server
+    pub fn tool_schemas(&self, tool: &str) -> Option<&ToolSchemas> {
+        self.tool_schemas.get(tool)
+    }
+
+    pub fn queue_stats(&self) -> QueueStats {
+        self.queue.stats()
+    }
+
+    /// Claims the next health check if `interval` has passed since the last
+    /// one and none is running.
+    pub fn begin_health_check(&self, interval: Duration) -> bool {
+        let mut health = self.health.lock().unwrap();
+        if health.checking || health.last_check.is_some_and(|t| t.elapsed() < interval) {
+            return false;
+        }
+        health.checking = true;
+        health.last_check = Some(Instant::now());
+        true
+    }
+
+    /// Records a ping outcome and returns the number of consecutive failures.
+    pub fn record_ping(&self, result: &Result<Duration, McpError>) -> u32 {
+        let mut health = self.health.lock().unwrap();
+        health.checking = false;
+        match result {
+            Ok(latency) => {
+                health.latency = Some(*latency);
+                health.failures = 0;
+            }
+            Err(_) => health.failures += 1,
+        }
+        health.failures
+    }
+
+    /// Latency of the last successful ping
+    pub fn last_ping(&self) -> Option<Duration> {
+        self.health.lock().unwrap().latency
+    }
+
+    /// Whether the last `threshold` or more pings failed
+    pub fn is_unhealthy(&self, threshold: u32) -> bool {
+        self.health.lock().unwrap().failures >= threshold.max(1)
+    }
+
+    /// Process id, for [`limits::usage`]
+    pub fn pid(&self) -> u32 {
+        self.process.lock().unwrap().id()
+    }
+
+    /// Describes the limit the server exceeded if its process was terminated
+    /// because of one of its `limits`.
+    pub fn limit_exceeded(&self) -> Option<String> {
+        let resource_limits = self.config.limits.as_ref()?;
+        let status = self.process.lock().unwrap().try_wait().ok()??;
+        let hint = *self.limit_hint.lock().unwrap();
+        limits::exceeded(&status, resource_limits, hint).map(|l| l.describe(resource_limits))
+    }
+
     #[allow(dead_code)]
     pub fn name(&self) -> &str {
         &self.name
     }
 }
 
+/// Writes one JSON-RPC message as a line and flushes it.
+fn write_message(stdin: &Mutex<ChildStdin>, taps: &Taps, message: &Value) -> std::io::Result<()> {
+    taps.frame(Direction::Out, message);
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    let mut stdin = stdin.lock().unwrap();
+    stdin.write_all(line.as_bytes())?;
+    stdin.flush()
+}
+
+/// Fails the request whose response was too large to buffer.
+fn fail_oversized(
+    server: &str,
+    id: Option<Id>,
+    size: usize,
+    limit: usize,
+    pending: &PendingRequests,
+) {
+    let sender = id.as_ref().and_then(|id| {
+        let mut pending = pending.lock().unwrap();
+        pending
+            .remove(id)
+            .or_else(|| id.alternate().and_then(|alt| pending.remove(&alt)))
+    });
+    match (id, sender) {
+        (Some(id), Some(sender)) => {
+            warn!(server, id = %id, "Response exceeds max message size: {} bytes", size);
+            let _ = sender.send(Err(McpError::Communication(format!(
+                "Response of {} bytes exceeds the maximum message size of {} bytes",
+                size, limit
+            ))));
+        }
+        (id, _) => warn!(
+            server,
+            "Discarded oversized message: id={:?}, size={} bytes",
+            id,
+            size
+        ),
+    }
+}
+
+/// Routes one inbound message from the server's stdout.
+fn handle_message(
+    server: &str,
+    message: &Value,
+    pending: &PendingRequests,
+    stdin: &Mutex<ChildStdin>,
+    taps: &Taps,
+) {
+    match jsonrpc::classify(message) {
+        Inbound::Response { id, result } => {
+            let sender = {
+                let mut pending = pending.lock().unwrap();
+                pending
+                    .remove(&id)
+                    .or_else(|| id.alternate().and_then(|alt| pending.remove(&alt)))
+            };
+            let Some(sender) = sender else {
+                warn!(
+                    server,
+                    id = %id,
+                    "Received response for unknown or already-completed request"
+                );
+                return;
+            };
+            match &result {
+                Ok(result) => debug!(
+                    server,
+                    id = %id,
+                    result_size = serde_json::to_string(result).map(|s| s.len()).unwrap_or(0),
+                    "Received success response"
+                ),
+                Err(error) => debug!(
+                    server,
+                    id = %id,
+                    code = error.code,
+                    "Received error response: {}",
+                    error.message
+                ),
+            }
+            let _ = sender.send(result.map_err(McpError::from));
+        }
+        Inbound::OrphanError(error) => {
+            // A null id means the server could not read the request id. It can
+            // only be attributed when exactly one request is waiting.
+            let mut pending = pending.lock().unwrap();
+            if pending.len() == 1 {
+                let id = pending.keys().next().cloned().expect("one pending request");
+                warn!(
+                    server,
+                    id = %id,
+                    "Received error with null id, failing the pending request: {}",
+                    error.message
+                );
+                if let Some(sender) = pending.remove(&id) {
+                    let _ = sender.send(Err(McpError::from(error)));
+                }
+            } else {
+                warn!(
+                    server,
+                    pending = pending.len(),
+                    code = error.code,
+                    "Received error with null id: {}",
+                    error.message
+                );
+            }
+        }
+        Inbound::Request { id, method } => {
+            let reply = if method == "ping" {
+                debug!(server, "Received ping from server");
+                json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+            } else {
+                // Other server-to-client requests (sampling, roots/list, ...) are not
+                // supported; answer so the server does not wait forever
+                debug!(
+                    server,
+                    method = %method,
+                    "Received request from server, replying method not found"
+                );
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": jsonrpc::METHOD_NOT_FOUND,
+                        "message": format!("Method not found: {}", method)
+                    }
+                })
+            };
+            if let Err(e) = write_message(stdin, taps, &reply) {
+                warn!(server, "Failed to reply: {}", e);
+            }
+        }
+        Inbound::Notification { method } => {
+            debug!(server, method = %method, "Received notification");
+        }
+        Inbound::Invalid(reason) => {
+            warn!(server, "Ignoring invalid message: {} | {}", reason, message);
+        }
+    }
+}
+
 impl Drop for McpClient {
     fn drop(&mut self) {
-        let _ = self.process.kill();
+        let process = self.process.get_mut().unwrap_or_else(|e| e.into_inner());
+        let _ = process.kill();
     }
 }
@@ -1,22 +1,29 @@
+use serde::Serialize;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::State;
 use tokio::sync::RwLock;
+use tracing::Instrument;
 
-use super::config::McpConfig;
+use super::audit::{self, AuditEntry, AuditQuery};
+use super::config::{McpConfig, McpProfile, ServerSettings, ToolPermission, WorkspaceConfig};
+use super::content;
 use super::manager::{McpManager, ServerInfo};
+use super::stats::StatsSnapshot;
+use crate::error::{CommandResult, ConfigError};
 
 type McpManagerState = Arc<RwLock<McpManager>>;
 
 #[tauri::command]
-pub async fn mcp_load_servers(manager: State<'_, McpManagerState>) -> Result<Vec<String>, String> {
-    manager.read().await.load_servers().await
+pub async fn mcp_load_servers(manager: State<'_, McpManagerState>) -> CommandResult<Vec<String>> {
+    Ok(manager.read().await.load_servers().await?)
 }
 
 #[tauri::command]
 pub async fn mcp_list_servers(
     manager: State<'_, McpManagerState>,
-) -> Result<Vec<ServerInfo>, String> {
+) -> CommandResult<Vec<ServerInfo>> {
     Ok(manager.read().await.list_servers().await)
 }
 
@@ -26,27 +33,30 @@ pub async fn mcp_call_tool(
     server: String,
     tool: String,
     arguments: Value,
-) -> Result<Value, String> {
-    eprintln!(
-        "[TAURI] mcp_call_tool START: server={}, tool={}",
-        server, tool
-    );
-    let result = manager
-        .read()
-        .await
-        .call_tool(&server, &tool, arguments)
-        .await;
-    match &result {
-        Ok(_) => eprintln!(
-            "[TAURI] mcp_call_tool SUCCESS: server={}, tool={}",
-            server, tool
-        ),
-        Err(e) => eprintln!(
-            "[TAURI] mcp_call_tool ERROR: server={}, tool={}, error={}",
-            server, tool, e
-        ),
+) -> CommandResult<Value> {
+    // Every record of this call, down to the JSON-RPC request, is logged in this span
+    let span = tracing::info_span!("tool_call", server = %server, tool = %tool);
+    async {
+        tracing::debug!("mcp_call_tool start");
+        let result = manager
+            .read()
+            .await
+            .call_tool(&server, &tool, arguments)
+            .await;
+        match &result {
+            Ok(_) => tracing::debug!("mcp_call_tool success"),
+            Err(e) => tracing::warn!("mcp_call_tool error: {}", e),
+        }
+        Ok(result?)
     }
-    result
+    .instrument(span)
+    .await
+}
+
+/// 查詢工具呼叫稽核紀錄（由新到舊）
+#[tauri::command]
+pub fn mcp_query_audit_log(query: Option<AuditQuery>) -> CommandResult<Vec<AuditEntry>> {
+    Ok(audit::query(&query.unwrap_or_default())?)
 }
 
 #[tauri::command]
@@ -54,29 +64,152 @@ pub async fn mcp_read_resource(
     manager: State<'_, McpManagerState>,
     server: String,
     uri: String,
-) -> Result<Value, String> {
-    manager.read().await.read_resource(&server, &uri).await
+) -> CommandResult<Value> {
+    let span = tracing::info_span!("read_resource", server = %server, uri = %uri);
+    async { Ok(manager.read().await.read_resource(&server, &uri).await?) }
+        .instrument(span)
+        .await
+}
+
+/// 讀取因超過大小限制而另存到快取目錄的內容
+#[tauri::command]
+pub fn mcp_read_cached_result(uri: String) -> CommandResult<Value> {
+    Ok(content::read_cached(&uri)?)
 }
 
 #[tauri::command]
 pub async fn mcp_stop_server(
     manager: State<'_, McpManagerState>,
     name: String,
-) -> Result<(), String> {
-    manager.read().await.stop_server(&name).await
+) -> CommandResult<()> {
+    Ok(manager.read().await.stop_server(&name).await?)
 }
 
+/// 各伺服器的執行統計（呼叫次數、錯誤、逾時、延遲百分位數、運作時間與重新啟動次數）
 #[tauri::command]
-pub fn mcp_get_config() -> Result<McpConfig, String> {
-    McpConfig::load()
+pub async fn mcp_get_stats(manager: State<'_, McpManagerState>) -> CommandResult<StatsSnapshot> {
+    Ok(manager.read().await.get_stats().await)
 }
 
 #[tauri::command]
-pub fn mcp_save_config(config: McpConfig) -> Result<(), String> {
-    config.save()
+pub fn mcp_get_config() -> CommandResult<McpConfig> {
+    Ok(McpConfig::load()?)
+}
+
+#[tauri::command]
+pub fn mcp_save_config(config: McpConfig) -> CommandResult<()> {
+    Ok(config.save()?)
 }
 
 #[tauri::command]
 pub fn mcp_get_config_path() -> String {
     McpConfig::config_path().to_string_lossy().to_string()
 }
+
+#[tauri::command]
+pub fn mcp_list_workspaces() -> CommandResult<Vec<WorkspaceConfig>> {
+    Ok(McpConfig::load()?.workspaces)
+}
+
+#[tauri::command]
+pub fn mcp_add_workspace(path: String) -> CommandResult<WorkspaceConfig> {
+    let mut config = McpConfig::load()?;
+    let workspace = config.add_workspace(&path)?;
+    config.save()?;
+    Ok(workspace)
+}
+
+#[tauri::command]
+pub fn mcp_remove_workspace(path: String) -> CommandResult<()> {
+    let mut config = McpConfig::load()?;
+    config.remove_workspace(&path)?;
+    Ok(config.save()?)
+}
+
+#[tauri::command]
+pub fn mcp_set_workspace_enabled(path: String, enabled: bool) -> CommandResult<()> {
+    let mut config = McpConfig::load()?;
+    config.set_workspace_enabled(&path, enabled)?;
+    Ok(config.save()?)
+}
+
+#[tauri::command]
+pub async fn mcp_set_project_server_trust(
+    manager: State<'_, McpManagerState>,
+    workspace: String,
+    name: String,
+    trusted: bool,
+) -> CommandResult<()> {
+    Ok(manager
+        .read()
+        .await
+        .set_project_trust(&workspace, &name, trusted)
+        .await?)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfilesInfo {
+    pub active: Option<String>,
+    pub profiles: HashMap<String, McpProfile>,
+}
+
+#[tauri::command]
+pub fn mcp_list_profiles() -> CommandResult<ProfilesInfo> {
+    let config = McpConfig::load()?;
+    Ok(ProfilesInfo {
+        active: config.active_profile,
+        profiles: config.profiles,
+    })
+}
+
+#[tauri::command]
+pub fn mcp_save_profile(name: String, profile: McpProfile) -> CommandResult<()> {
+    let mut config = McpConfig::load()?;
+    config.profiles.insert(name, profile);
+    Ok(config.save()?)
+}
+
+/// 刪除設定檔；刪除的是目前的設定檔時，切換回「全部伺服器」並套用
+#[tauri::command]
+pub async fn mcp_delete_profile(
+    manager: State<'_, McpManagerState>,
+    name: String,
+) -> CommandResult<()> {
+    let mut config = McpConfig::load()?;
+    config
+        .profiles
+        .remove(&name)
+        .ok_or_else(|| ConfigError::ProfileNotFound(name.clone()))?;
+    let was_active = config.active_profile.as_deref() == Some(name.as_str());
+    config.save()?;
+    if was_active {
+        manager.read().await.switch_profile(None).await?;
+    }
+    Ok(())
+}
+
+/// 切換設定檔並套用差異（`name` 為 null 時啟動所有伺服器）
+#[tauri::command]
+pub async fn mcp_switch_profile(
+    manager: State<'_, McpManagerState>,
+    name: Option<String>,
+) -> CommandResult<Vec<String>> {
+    Ok(manager.read().await.switch_profile(name).await?)
+}
+
+#[tauri::command]
+pub fn mcp_get_server_settings() -> CommandResult<HashMap<String, ServerSettings>> {
+    Ok(McpConfig::load()?.server_settings)
+}
+
+/// 設定工具權限：`tool` 為 null 時設定整個伺服器，`permission` 為 null 時清除
+#[tauri::command]
+pub fn mcp_set_tool_permission(
+    server: String,
+    tool: Option<String>,
+    permission: Option<ToolPermission>,
+) -> CommandResult<()> {
+    let mut config = McpConfig::load()?;
+    config.set_tool_permission(&server, tool.as_deref(), permission);
+    Ok(config.save()?)
+}
@@ -1,14 +1,175 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::error::{AppResult, ConfigError};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct McpServerConfig {
     pub command: String,
     #[serde(default)]
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// 子程序的工作目錄（未設定時沿用應用程式的工作目錄）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// 停用但保留設定，不會被啟動
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    /// 從應用程式繼承哪些環境變數
+    #[serde(
+        default,
+        rename = "inheritEnv",
+        skip_serializing_if = "EnvInheritance::is_all"
+    )]
+    pub inherit_env: EnvInheritance,
+    /// 單一訊息的大小上限（bytes），超過時對應的請求會失敗
+    #[serde(
+        default,
+        rename = "maxMessageSize",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_message_size: Option<usize>,
+    /// 同時處理中的請求上限，超過時依序排隊（未設定時不限制）
+    #[serde(default, rename = "maxInFlight", skip_serializing_if = "Option::is_none")]
+    pub max_in_flight: Option<usize>,
+    /// 排隊中的請求上限，佇列已滿時新請求直接失敗（預設 32）
+    #[serde(default, rename = "maxQueued", skip_serializing_if = "Option::is_none")]
+    pub max_queued: Option<usize>,
+    /// 啟動時機：`eager`（載入時啟動，預設）或 `lazy`（第一次使用時才啟動）
+    #[serde(default, skip_serializing_if = "Startup::is_eager")]
+    pub startup: Startup,
+    /// 閒置超過指定分鐘數後停止伺服器，下次使用時再啟動
+    #[serde(
+        default,
+        rename = "idleTimeoutMinutes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub idle_timeout_minutes: Option<u64>,
+    /// 定期以 MCP `ping` 確認伺服器仍有回應（未設定時不檢查）
+    #[serde(
+        default,
+        rename = "healthCheck",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health_check: Option<HealthCheck>,
+    /// 在沙箱中執行（僅 Linux）：限制可存取的路徑與網路
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
+    /// 子程序的資源上限（僅 Linux 與 macOS）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceLimits>,
+    /// 將收發的 JSON-RPC 訊息記錄到 `recordings/` 目錄，可用 `mcp replay` 重播
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub record: bool,
+}
+
+/// 沙箱設定
+///
+/// 系統目錄、指令所在目錄與 `PATH` 一律可讀取；`/tmp`、`/dev` 與 `cwd` 可寫入。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SandboxConfig {
+    /// 額外可讀取的路徑
+    pub read: Vec<String>,
+    /// 額外可讀寫的路徑
+    pub write: Vec<String>,
+    /// 允許網路存取（預設允許）
+    pub network: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            read: Vec::new(),
+            write: Vec::new(),
+            network: true,
+        }
+    }
+}
+
+/// 資源上限，啟動時以 rlimit 套用到伺服器程序（子程序會繼承）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLimits {
+    /// 虛擬記憶體（位址空間）上限，單位 MB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// CPU 時間上限（秒），超過時程序會收到 SIGXCPU
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<u64>,
+    /// 可同時開啟的檔案數上限
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_files: Option<u64>,
+    /// 程序數上限（以使用者為單位計算，包含既有的程序）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processes: Option<u64>,
+}
+
+/// 健康檢查設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HealthCheck {
+    /// 兩次 ping 之間的秒數（預設 60，0 表示不檢查）
+    pub interval_seconds: u64,
+    /// 等待 ping 回應的秒數（預設 10）
+    pub timeout_seconds: u64,
+    /// 連續失敗幾次後標記為 unhealthy（預設 3）
+    pub failure_threshold: u32,
+    /// 標記為 unhealthy 時自動重新啟動伺服器
+    pub restart: bool,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 60,
+            timeout_seconds: 10,
+            failure_threshold: 3,
+            restart: false,
+        }
+    }
+}
+
+/// 伺服器啟動時機
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Startup {
+    /// 載入設定時立即啟動（預設）
+    #[default]
+    Eager,
+    /// 第一次呼叫工具或讀取資源時才啟動
+    Lazy,
+}
+
+impl Startup {
+    fn is_eager(&self) -> bool {
+        *self == Startup::Eager
+    }
+}
+
+/// 環境變數繼承策略
+///
+/// JSON 格式：`"all"`、`"clean"` 或 `{ "allowlist": ["PATH", "HOME"] }`。
+/// 無論哪種策略，`env` 中的設定值都會套用。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvInheritance {
+    /// 繼承應用程式的完整環境（預設）
+    #[default]
+    All,
+    /// 只繼承列出的變數
+    Allowlist(Vec<String>),
+    /// 不繼承任何變數
+    Clean,
+}
+
+impl EnvInheritance {
+    fn is_all(&self) -> bool {
+        *self == EnvInheritance::All
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -16,6 +177,264 @@ pub struct McpConfig {
     /// MCP 伺服器設定（序列化時使用 mcpServers 以相容官方格式）
     #[serde(rename = "mcpServers")]
     pub mcp_servers: HashMap<String, McpServerConfig>,
+    /// 已註冊的專案目錄，其中的 `.mcp.json` 會合併進來
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<WorkspaceConfig>,
+    /// 具名的伺服器設定檔
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, McpProfile>,
+    /// 目前使用的設定檔（未設定時啟動所有伺服器）
+    #[serde(rename = "activeProfile", skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// 各伺服器的工具設定（key 為伺服器名稱，包含 `ext_*`、`proj_*`）
+    #[serde(rename = "serverSettings", skip_serializing_if = "HashMap::is_empty")]
+    pub server_settings: HashMap<String, ServerSettings>,
+    /// 工具呼叫稽核紀錄設定
+    #[serde(skip_serializing_if = "AuditConfig::is_default")]
+    pub audit: AuditConfig,
+    /// 工具結果與資源內容的大小限制
+    #[serde(rename = "resultLimits", skip_serializing_if = "ResultLimits::is_default")]
+    pub result_limits: ResultLimits,
+    /// 執行統計設定
+    #[serde(skip_serializing_if = "StatsConfig::is_default")]
+    pub stats: StatsConfig,
+    /// 記錄檔設定
+    #[serde(skip_serializing_if = "LoggingConfig::is_default")]
+    pub logging: LoggingConfig,
+    /// 供其他 MCP 客戶端共用伺服器的本機閘道設定
+    #[serde(skip_serializing_if = "GatewayConfig::is_default")]
+    pub gateway: GatewayConfig,
+    /// 其他最上層欄位（例如官方應用程式的設定），儲存時原樣寫回
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 工具呼叫權限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPermission {
+    /// 直接執行（預設）
+    #[default]
+    Allow,
+    /// 每次執行前詢問使用者
+    Ask,
+    /// 拒絕執行
+    Deny,
+}
+
+/// 單一伺服器的工具設定
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ServerSettings {
+    /// 此伺服器所有工具的預設權限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<ToolPermission>,
+    /// 加在此伺服器所有工具名稱前的字首（如 `"github_"`），用來區分同名工具
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// 個別工具的設定（key 為伺服器提供的原始工具名稱）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tools: HashMap<String, ToolSettings>,
+}
+
+impl ServerSettings {
+    fn is_empty(&self) -> bool {
+        self.permission.is_none() && self.prefix.is_none() && self.tools.is_empty()
+    }
+}
+
+/// 單一工具的設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<ToolPermission>,
+    /// 對模型顯示的名稱（取代原始名稱，不套用伺服器字首）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// 取代伺服器提供的工具說明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 停用的工具不會列出，也無法呼叫
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            permission: None,
+            alias: None,
+            description: None,
+            enabled: true,
+        }
+    }
+}
+
+impl ToolSettings {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 伺服器設定檔（profile）：啟用的伺服器子集與額外的環境變數
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct McpProfile {
+    /// 啟用的伺服器名稱（`mcpServers` 的 key 或專案伺服器的 `proj_*` 名稱）
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// 啟用的 Extension ID
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// 套用到此設定檔中所有伺服器的環境變數（覆蓋伺服器本身的設定）
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// 稽核紀錄中參數的記錄方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditArguments {
+    /// 記錄參數，敏感欄位以 `[REDACTED]` 取代（預設）
+    #[default]
+    Redacted,
+    /// 只記錄參數的雜湊值
+    Hash,
+}
+
+/// 工具呼叫稽核紀錄（`mcp-audit.jsonl`）設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    pub arguments: AuditArguments,
+    /// 欄位名稱包含其中任一字串（不分大小寫）時遮蔽其值，附加於預設清單之後
+    pub redact_keys: Vec<String>,
+    /// 單一紀錄檔的大小上限（bytes），超過時輪替
+    pub max_file_size: u64,
+    /// 保留的輪替檔案數
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            arguments: AuditArguments::default(),
+            redact_keys: Vec::new(),
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+impl AuditConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 工具結果大小限制（`resultLimits`）
+///
+/// 超過限制的內容會截斷；`spill` 開啟時完整內容另存到快取目錄並以 URI 引用。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResultLimits {
+    /// 單一內容區塊的大小上限（bytes）；圖片與音訊只受 `max_result_size` 限制
+    pub max_block_size: usize,
+    /// 整個結果的大小上限（bytes）
+    pub max_result_size: usize,
+    /// 截斷後保留的文字長度（bytes）
+    pub preview_size: usize,
+    /// 是否將超過限制的內容另存到快取目錄
+    pub spill: bool,
+}
+
+impl Default for ResultLimits {
+    fn default() -> Self {
+        Self {
+            max_block_size: 256 * 1024,
+            max_result_size: 2 * 1024 * 1024,
+            preview_size: 8 * 1024,
+            spill: true,
+        }
+    }
+}
+
+impl ResultLimits {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 執行統計（`mcp_get_stats`）設定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StatsConfig {
+    /// 每隔幾秒將統計快照寫入 `mcp-stats.json`（0 表示不寫入，預設）
+    pub snapshot_interval_seconds: u64,
+}
+
+impl StatsConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 記錄檔設定（`logging`），環境變數 `RUST_LOG` 優先
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LoggingConfig {
+    /// 記錄等級與模組篩選，格式同 `RUST_LOG`（如 `"info"` 或 `"warn,claude_desktop::mcp=debug"`）
+    pub level: String,
+    /// 保留的每日記錄檔數量
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            max_files: 7,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 本機 MCP 閘道（`gateway`）設定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GatewayConfig {
+    /// 應用程式執行時開啟閘道（預設關閉）
+    pub enabled: bool,
+    /// Unix socket 路徑（預設為設定檔目錄下的 `mcp-gateway.sock`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_path: Option<String>,
+}
+
+impl GatewayConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 專案目錄設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    pub path: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
 }
 
 impl<'de> Deserialize<'de> for McpConfig {
@@ -24,35 +443,124 @@ impl<'de> Deserialize<'de> for McpConfig {
         D: Deserializer<'de>,
     {
         let value = serde_json::Value::deserialize(deserializer)?;
+        Self::from_value(&value).map_err(serde::de::Error::custom)
+    }
+}
 
-        let mcp_servers = if let Some(obj) = value.as_object() {
-            // 優先嘗試 mcpServers (camelCase) - 官方格式
-            if let Some(servers) = obj.get("mcpServers") {
-                serde_json::from_value(servers.clone()).unwrap_or_default()
-            }
-            // 然後嘗試 mcp_servers (snake_case)
-            else if let Some(servers) = obj.get("mcp_servers") {
-                serde_json::from_value(servers.clone()).unwrap_or_default()
-            } else {
-                HashMap::new()
+/// 逐一解析伺服器設定，任何一個格式錯誤時回傳指出該伺服器的錯誤，
+/// 而不是讓整個清單變成空的
+fn parse_servers(
+    key: &str,
+    servers: &serde_json::Value,
+) -> Result<HashMap<String, McpServerConfig>, ConfigError> {
+    let entries = match servers {
+        serde_json::Value::Null => return Ok(HashMap::new()),
+        serde_json::Value::Object(entries) => entries,
+        _ => {
+            return Err(ConfigError::ParseError {
+                key: key.to_string(),
+                reason: "expected an object".to_string(),
+            })
+        }
+    };
+
+    let mut parsed = HashMap::new();
+    let mut failed = Vec::new();
+    for (name, server) in entries {
+        match McpServerConfig::deserialize(server) {
+            Ok(config) => {
+                parsed.insert(name.clone(), config);
             }
-        } else {
-            HashMap::new()
-        };
+            Err(e) => failed.push(format!("server '{}': {}", name, e)),
+        }
+    }
+    if !failed.is_empty() {
+        return Err(ConfigError::ParseError {
+            key: key.to_string(),
+            reason: failed.join("; "),
+        });
+    }
+    Ok(parsed)
+}
 
-        Ok(McpConfig { mcp_servers })
+/// 解析選填的區段。區段存在但格式錯誤時回傳錯誤，而不是改用預設值，
+/// 以免之後的儲存以預設值覆蓋使用者的設定
+fn parse_section<T: DeserializeOwned + Default>(
+    obj: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Result<T, ConfigError> {
+    match obj.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(T::default()),
+        Some(section) => T::deserialize(section).map_err(|e| ConfigError::ParseError {
+            key: key.to_string(),
+            reason: e.to_string(),
+        }),
     }
 }
 
+/// 由 `McpConfig` 解析的最上層欄位，其餘欄位原樣保存在 `extra`
+const KNOWN_KEYS: &[&str] = &[
+    "mcpServers",
+    "mcp_servers",
+    "workspaces",
+    "profiles",
+    "activeProfile",
+    "serverSettings",
+    "audit",
+    "resultLimits",
+    "stats",
+    "logging",
+    "gateway",
+];
+
 impl McpConfig {
+    fn from_value(value: &serde_json::Value) -> Result<Self, ConfigError> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| ConfigError::InvalidFormat("expected a JSON object".to_string()))?;
+
+        // 優先使用 mcpServers (camelCase) - 官方格式，然後是 mcp_servers (snake_case)
+        let mcp_servers = match (obj.get("mcpServers"), obj.get("mcp_servers")) {
+            (Some(servers), _) => parse_servers("mcpServers", servers)?,
+            (None, Some(servers)) => parse_servers("mcp_servers", servers)?,
+            (None, None) => HashMap::new(),
+        };
+
+        let extra = obj
+            .iter()
+            .filter(|(key, _)| !KNOWN_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        Ok(McpConfig {
+            mcp_servers,
+            workspaces: parse_section(obj, "workspaces")?,
+            profiles: parse_section(obj, "profiles")?,
+            active_profile: parse_section(obj, "activeProfile")?,
+            server_settings: parse_section(obj, "serverSettings")?,
+            audit: parse_section(obj, "audit")?,
+            result_limits: parse_section(obj, "resultLimits")?,
+            stats: parse_section(obj, "stats")?,
+            logging: parse_section(obj, "logging")?,
+            gateway: parse_section(obj, "gateway")?,
+            extra,
+        })
+    }
+
     pub fn config_path() -> PathBuf {
+        // Tests must not touch the user's config
+        #[cfg(test)]
+        let config_dir = std::env::temp_dir()
+            .join("claude-desktop-tests")
+            .join("Claude");
+        #[cfg(not(test))]
         let config_dir = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("Claude");
         config_dir.join("claude_desktop_config.json")
     }
 
-    pub fn load() -> Result<Self, String> {
+    pub fn load() -> AppResult<Self> {
         let path = Self::config_path();
 
         if !path.exists() {
@@ -60,22 +568,244 @@ impl McpConfig {
         }
 
         let content =
-            std::fs::read_to_string(&path).map_err(|e| format!("Failed to read config: {}", e))?;
+            std::fs::read_to_string(&path).map_err(|e| ConfigError::ReadFailed(e.to_string()))?;
+
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| ConfigError::InvalidFormat(e.to_string()))?;
+        Ok(Self::from_value(&value)?)
+    }
+
+    /// 目前使用的設定檔（名稱不存在時視為未設定）
+    pub fn active_profile(&self) -> Option<&McpProfile> {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+    }
+
+    pub fn profile_includes_server(&self, name: &str) -> bool {
+        self.active_profile()
+            .map(|p| p.servers.iter().any(|s| s == name))
+            .unwrap_or(true)
+    }
 
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))
+    pub fn profile_includes_extension(&self, extension_id: &str) -> bool {
+        self.active_profile()
+            .map(|p| p.extensions.iter().any(|e| e == extension_id))
+            .unwrap_or(true)
     }
 
-    pub fn save(&self) -> Result<(), String> {
+    /// 將目前設定檔的環境變數合併到伺服器設定
+    pub fn apply_profile_env(&self, server: &mut McpServerConfig) {
+        if let Some(profile) = self.active_profile() {
+            server
+                .env
+                .extend(profile.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+
+    /// 工具的有效權限：工具設定 > 伺服器設定 > 允許
+    pub fn tool_permission(&self, server: &str, tool: &str) -> ToolPermission {
+        let Some(settings) = self.server_settings.get(server) else {
+            return ToolPermission::default();
+        };
+        settings
+            .tools
+            .get(tool)
+            .and_then(|t| t.permission)
+            .or(settings.permission)
+            .unwrap_or_default()
+    }
+
+    /// 設定伺服器（`tool` 為 None）或單一工具的權限，`permission` 為 None 時清除
+    pub fn set_tool_permission(
+        &mut self,
+        server: &str,
+        tool: Option<&str>,
+        permission: Option<ToolPermission>,
+    ) {
+        let settings = self.server_settings.entry(server.to_string()).or_default();
+        match tool {
+            Some(tool) => {
+                settings.tools.entry(tool.to_string()).or_default().permission = permission;
+                if settings.tools[tool].is_default() {
+                    settings.tools.remove(tool);
+                }
+            }
+            None => settings.permission = permission,
+        }
+        if settings.is_empty() {
+            self.server_settings.remove(server);
+        }
+    }
+
+    /// 註冊專案目錄（路徑會正規化為絕對路徑）
+    pub fn add_workspace(&mut self, path: &str) -> Result<WorkspaceConfig, String> {
+        let canonical = std::fs::canonicalize(path)
+            .map_err(|e| format!("Invalid workspace path '{}': {}", path, e))?;
+        if !canonical.is_dir() {
+            return Err(format!("Workspace '{}' is not a directory", path));
+        }
+        let canonical = canonical.to_string_lossy().to_string();
+
+        if let Some(existing) = self.workspaces.iter().find(|w| w.path == canonical) {
+            return Ok(existing.clone());
+        }
+
+        let workspace = WorkspaceConfig {
+            path: canonical,
+            enabled: true,
+        };
+        self.workspaces.push(workspace.clone());
+        Ok(workspace)
+    }
+
+    pub fn remove_workspace(&mut self, path: &str) -> Result<(), String> {
+        let before = self.workspaces.len();
+        self.workspaces.retain(|w| w.path != path);
+        if self.workspaces.len() == before {
+            return Err(format!("Workspace '{}' not found", path));
+        }
+        Ok(())
+    }
+
+    pub fn set_workspace_enabled(&mut self, path: &str, enabled: bool) -> Result<(), String> {
+        let workspace = self
+            .workspaces
+            .iter_mut()
+            .find(|w| w.path == path)
+            .ok_or_else(|| format!("Workspace '{}' not found", path))?;
+        workspace.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn save(&self) -> AppResult<()> {
         let path = Self::config_path();
 
         if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ConfigError::WriteFailed(format!("cannot create config directory: {}", e))
+            })?;
         }
 
-        let content = serde_json::to_string_pretty(self)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        let content = serde_json::to_string_pretty(self)?;
+
+        std::fs::write(&path, content).map_err(|e| ConfigError::WriteFailed(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_options() {
+        let config: McpConfig = serde_json::from_str(
+            r#"{"mcpServers": {
+                "a": {"command": "a", "cwd": "/tmp", "disabled": true, "inheritEnv": "clean"},
+                "b": {"command": "b", "inheritEnv": {"allowlist": ["PATH"]}},
+                "c": {"command": "c", "healthCheck": {"intervalSeconds": 15, "restart": true}}
+            }}"#,
+        )
+        .unwrap();
+        let a = &config.mcp_servers["a"];
+        assert_eq!(a.cwd.as_deref(), Some("/tmp"));
+        assert!(a.disabled);
+        assert_eq!(a.inherit_env, EnvInheritance::Clean);
+        assert_eq!(
+            config.mcp_servers["b"].inherit_env,
+            EnvInheritance::Allowlist(vec!["PATH".to_string()])
+        );
+        assert_eq!(
+            config.mcp_servers["c"].health_check,
+            Some(HealthCheck {
+                interval_seconds: 15,
+                restart: true,
+                ..Default::default()
+            })
+        );
+
+        // Defaults are not written back
+        let json = serde_json::to_value(&config.mcp_servers["b"]).unwrap();
+        assert!(json.get("disabled").is_none());
+        assert!(json.get("cwd").is_none());
+        assert!(json.get("healthCheck").is_none());
+    }
+
+    #[test]
+    fn test_invalid_server_is_reported() {
+        let value = serde_json::json!({"mcpServers": {
+            "good": {"command": "a"},
+            "bad": {"command": "b", "startup": "Lazy"}
+        }});
+        let err = McpConfig::from_value(&value).unwrap_err();
+        assert!(matches!(&err, ConfigError::ParseError { key, .. } if key == "mcpServers"));
+        let message = err.to_string();
+        assert!(message.contains("server 'bad'"));
+        assert!(!message.contains("'good'"));
+    }
+
+    #[test]
+    fn test_malformed_section_fails_and_unknown_keys_are_kept() {
+        let value = serde_json::json!({
+            "mcpServers": {"a": {"command": "a"}},
+            "serverSettings": {"a": {"permission": "nope"}}
+        });
+        let err = McpConfig::from_value(&value).unwrap_err();
+        assert!(matches!(err, ConfigError::ParseError { key, .. } if key == "serverSettings"));
+
+        let value = serde_json::json!({
+            "mcpServers": {"a": {"command": "a"}},
+            "globalShortcut": "Ctrl+Space",
+            "mcp_servers": {"old": {"command": "old"}}
+        });
+        let config = McpConfig::from_value(&value).unwrap();
+        let saved = serde_json::to_value(&config).unwrap();
+        assert_eq!(saved["globalShortcut"], "Ctrl+Space");
+        assert_eq!(saved["mcpServers"]["a"]["command"], "a");
+        // The legacy key is not written next to mcpServers
+        assert!(saved.get("mcp_servers").is_none());
+    }
+
+    #[test]
+    fn test_active_profile() {
+        let mut config: McpConfig = serde_json::from_str(
+            r#"{
+                "mcpServers": {"a": {"command": "a"}, "b": {"command": "b"}},
+                "profiles": {"work": {"servers": ["a"], "extensions": ["ext1"], "env": {"MODE": "work"}}},
+                "activeProfile": "work"
+            }"#,
+        )
+        .unwrap();
+        assert!(config.profile_includes_server("a"));
+        assert!(!config.profile_includes_server("b"));
+        assert!(config.profile_includes_extension("ext1"));
+        assert!(!config.profile_includes_extension("ext2"));
+
+        let mut server = config.mcp_servers["a"].clone();
+        config.apply_profile_env(&mut server);
+        assert_eq!(server.env["MODE"], "work");
+
+        // No active profile: everything is included
+        config.active_profile = None;
+        assert!(config.profile_includes_server("b"));
+        assert!(config.profile_includes_extension("ext2"));
+    }
+
+    #[test]
+    fn test_tool_permission() {
+        let mut config = McpConfig::default();
+        assert_eq!(config.tool_permission("s", "t"), ToolPermission::Allow);
+
+        config.set_tool_permission("s", None, Some(ToolPermission::Ask));
+        config.set_tool_permission("s", Some("rm"), Some(ToolPermission::Deny));
+        assert_eq!(config.tool_permission("s", "t"), ToolPermission::Ask);
+        assert_eq!(config.tool_permission("s", "rm"), ToolPermission::Deny);
+        assert_eq!(config.tool_permission("other", "rm"), ToolPermission::Allow);
 
-        std::fs::write(&path, content).map_err(|e| format!("Failed to write config: {}", e))
+        // Clearing both levels removes the entry
+        config.set_tool_permission("s", Some("rm"), None);
+        config.set_tool_permission("s", None, None);
+        assert!(config.server_settings.is_empty());
     }
 }
@@ -0,0 +1,638 @@
+//! Size limits for tool results and resource contents.
+//!
+//! Large results are serialized into the IPC reply to the webview, so a tool
+//! that dumps megabytes of text or base64 data can freeze the page. Results
+//! are walked block by block against `resultLimits`:
+//!
+//! - Blocks within the limits (including `image`, `audio` and `resource`
+//!   blocks) pass through unchanged, MIME types included.
+//! - Images and audio (including image and audio resource blobs) are only
+//!   held to `maxResultSize`; a screenshot is useless as a link.
+//! - Oversized text is cut to a preview. With `spill` enabled the full content
+//!   is written to the cache directory and referenced by a `file://` URI in a
+//!   `resource_link` block, readable via [`read_cached`].
+//! - Oversized binary data is replaced by a note (and a link when spilled).
+
+use base64::Engine as _;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::config::ResultLimits;
+
+/// Spilled files older than this are removed
+const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::STANDARD;
+
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("Claude")
+        .join("mcp-results")
+}
+
+/// Applies the limits to a `tools/call` result (`content` blocks).
+pub fn limit_tool_result(result: Value, limits: &ResultLimits) -> Value {
+    let dir = cache_dir();
+    limit_tool_result_in(result, limits, limits.spill.then_some(dir.as_path()))
+}
+
+/// Applies the limits to a `resources/read` result (`contents` entries).
+pub fn limit_resource_result(result: Value, limits: &ResultLimits) -> Value {
+    let dir = cache_dir();
+    limit_resource_result_in(result, limits, limits.spill.then_some(dir.as_path()))
+}
+
+fn limit_tool_result_in(mut result: Value, limits: &ResultLimits, spill: Option<&Path>) -> Value {
+    let Some(obj) = result.as_object_mut() else {
+        return result;
+    };
+    let mut budget = limits.max_result_size;
+
+    if let Some(Value::Array(blocks)) = obj.remove("content") {
+        let mut limited = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let size = block_size(&block);
+            if size <= block_limit(&block, limits).min(budget) {
+                budget -= size;
+                limited.push(block);
+                continue;
+            }
+            for replacement in shrink_block(block, size, limits, spill) {
+                budget = budget.saturating_sub(block_size(&replacement));
+                limited.push(replacement);
+            }
+        }
+        obj.insert("content".to_string(), Value::Array(limited));
+    }
+
+    // structuredContent usually mirrors the text content; drop it rather than truncate
+    let structured_size = obj
+        .get("structuredContent")
+        .map(|v| serde_json::to_vec(v).map(|b| b.len()).unwrap_or(0))
+        .unwrap_or(0);
+    if structured_size > budget.min(limits.max_block_size) {
+        let structured = obj.remove("structuredContent").unwrap_or(Value::Null);
+        let bytes = structured.to_string().into_bytes();
+        let mut note = format!(
+            "[structuredContent omitted: {} exceeds the result size limit",
+            format_size(bytes.len())
+        );
+        let link = spill.and_then(|dir| store(dir, &bytes, "application/json").ok());
+        if let Some(uri) = &link {
+            note.push_str(&format!(". Full content: {}", uri));
+        }
+        note.push(']');
+
+        let content = obj
+            .entry("content")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(blocks) = content {
+            blocks.push(json!({"type": "text", "text": note}));
+            if let Some(uri) = link {
+                blocks.push(resource_link(
+                    &uri,
+                    "structuredContent.json",
+                    "application/json",
+                    bytes.len(),
+                ));
+            }
+        }
+    }
+
+    result
+}
+
+fn limit_resource_result_in(
+    mut result: Value,
+    limits: &ResultLimits,
+    spill: Option<&Path>,
+) -> Value {
+    let Some(Value::Array(contents)) = result.get_mut("contents") else {
+        return result;
+    };
+    let mut budget = limits.max_result_size;
+
+    for entry in contents.iter_mut() {
+        let size = entry_size(entry);
+        if size <= entry_limit(entry, limits).min(budget) {
+            budget -= size;
+            continue;
+        }
+        let Some(obj) = entry.as_object_mut() else {
+            continue;
+        };
+        let mime = obj
+            .get("mimeType")
+            .and_then(|m| m.as_str())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+
+        if let Some(text) = obj
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map(|t| t.to_string())
+        {
+            let mime = if mime.starts_with("text/") || mime.contains("json") {
+                mime
+            } else {
+                "text/plain".to_string()
+            };
+            let link = spill.and_then(|dir| store(dir, text.as_bytes(), &mime).ok());
+            let preview = format!(
+                "{}\n\n{}",
+                truncate_str(&text, limits.preview_size),
+                truncation_note(text.len(), limits.preview_size, link.as_deref())
+            );
+            obj.insert("text".to_string(), Value::String(preview));
+        } else if let Some(Value::String(blob)) = obj.remove("blob") {
+            let bytes = BASE64.decode(blob.as_bytes()).ok();
+            let link = match (spill, &bytes) {
+                (Some(dir), Some(bytes)) => store(dir, bytes, &mime).ok(),
+                _ => None,
+            };
+            let size = bytes.map(|b| b.len()).unwrap_or(blob.len());
+            obj.insert(
+                "mimeType".to_string(),
+                Value::String("text/plain".to_string()),
+            );
+            obj.insert(
+                "text".to_string(),
+                Value::String(omitted_note(&mime, size, link.as_deref())),
+            );
+        }
+        budget = budget.saturating_sub(entry_size(entry));
+    }
+
+    result
+}
+
+/// Replaces an oversized content block with a preview and/or a link.
+fn shrink_block(
+    block: Value,
+    size: usize,
+    limits: &ResultLimits,
+    spill: Option<&Path>,
+) -> Vec<Value> {
+    let kind = block.get("type").and_then(|t| t.as_str()).unwrap_or("");
+    let mime = block
+        .get("mimeType")
+        .or_else(|| block.pointer("/resource/mimeType"))
+        .and_then(|m| m.as_str())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let (text, data) = match kind {
+        "text" => (block.get("text").and_then(|t| t.as_str()), None),
+        "image" | "audio" => (None, block.get("data").and_then(|d| d.as_str())),
+        "resource" => (
+            block.pointer("/resource/text").and_then(|t| t.as_str()),
+            block.pointer("/resource/blob").and_then(|b| b.as_str()),
+        ),
+        _ => (None, None),
+    };
+
+    if let Some(text) = text {
+        let mime = if kind == "text" {
+            "text/plain".to_string()
+        } else {
+            mime
+        };
+        let link = spill.and_then(|dir| store(dir, text.as_bytes(), &mime).ok());
+        let mut blocks = vec![json!({
+            "type": "text",
+            "text": format!(
+                "{}\n\n{}",
+                truncate_str(text, limits.preview_size),
+                truncation_note(text.len(), limits.preview_size, link.as_deref())
+            )
+        })];
+        if let Some(uri) = link {
+            blocks.push(resource_link(&uri, &link_name(&block), &mime, text.len()));
+        }
+        return blocks;
+    }
+
+    if let Some(data) = data {
+        let bytes = BASE64.decode(data.as_bytes()).ok();
+        let link = match (spill, &bytes) {
+            (Some(dir), Some(bytes)) => store(dir, bytes, &mime).ok(),
+            _ => None,
+        };
+        let size = bytes.as_ref().map(|b| b.len()).unwrap_or(data.len());
+        let mut blocks =
+            vec![json!({"type": "text", "text": omitted_note(&mime, size, link.as_deref())})];
+        if let Some(uri) = link {
+            blocks.push(resource_link(&uri, &link_name(&block), &mime, size));
+        }
+        return blocks;
+    }
+
+    // Unknown block type: keep it as JSON
+    let bytes = block.to_string().into_bytes();
+    let link = spill.and_then(|dir| store(dir, &bytes, "application/json").ok());
+    let mut blocks = vec![json!({
+        "type": "text",
+        "text": omitted_note(&format!("'{}' block", kind), size, link.as_deref())
+    })];
+    if let Some(uri) = link {
+        blocks.push(resource_link(
+            &uri,
+            &link_name(&block),
+            "application/json",
+            bytes.len(),
+        ));
+    }
+    blocks
+}
+
+/// Per-block limit of a content block; images and audio only count against the result size.
+fn block_limit(block: &Value, limits: &ResultLimits) -> usize {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("image" | "audio") => usize::MAX,
+        Some("resource") => block
+            .get("resource")
+            .map(|r| entry_limit(r, limits))
+            .unwrap_or(limits.max_block_size),
+        _ => limits.max_block_size,
+    }
+}
+
+/// Per-entry limit of a resource contents entry; image and audio blobs are exempt.
+fn entry_limit(entry: &Value, limits: &ResultLimits) -> usize {
+    let mime = entry.get("mimeType").and_then(|m| m.as_str()).unwrap_or("");
+    if entry.get("blob").is_some() && (mime.starts_with("image/") || mime.starts_with("audio/")) {
+        usize::MAX
+    } else {
+        limits.max_block_size
+    }
+}
+
+/// Size of the payload carried by a content block.
+fn block_size(block: &Value) -> usize {
+    let str_len = |v: Option<&Value>| v.and_then(|s| s.as_str()).map(|s| s.len());
+    str_len(block.get("text"))
+        .or_else(|| str_len(block.get("data")))
+        .or_else(|| block.get("resource").map(entry_size))
+        .unwrap_or_else(|| serde_json::to_vec(block).map(|b| b.len()).unwrap_or(0))
+}
+
+/// Size of the payload of a resource contents entry.
+fn entry_size(entry: &Value) -> usize {
+    ["text", "blob"]
+        .iter()
+        .filter_map(|k| entry.get(*k).and_then(|v| v.as_str()))
+        .map(|s| s.len())
+        .sum()
+}
+
+fn resource_link(uri: &str, name: &str, mime: &str, size: usize) -> Value {
+    json!({
+        "type": "resource_link",
+        "uri": uri,
+        "name": name,
+        "mimeType": mime,
+        "size": size
+    })
+}
+
+fn link_name(block: &Value) -> String {
+    block
+        .pointer("/resource/uri")
+        .and_then(|u| u.as_str())
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| {
+            let kind = block
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or("content");
+            format!("{} content", kind)
+        })
+}
+
+fn truncation_note(total: usize, shown: usize, link: Option<&str>) -> String {
+    match link {
+        Some(uri) => format!(
+            "[Truncated: {} total, showing the first {}. Full content: {}]",
+            format_size(total),
+            format_size(shown),
+            uri
+        ),
+        None => format!(
+            "[Truncated: {} total, showing the first {}]",
+            format_size(total),
+            format_size(shown)
+        ),
+    }
+}
+
+fn omitted_note(what: &str, size: usize, link: Option<&str>) -> String {
+    match link {
+        Some(uri) => format!("[{} ({}) saved to {}]", what, format_size(size), uri),
+        None => format!(
+            "[{} ({}) omitted: exceeds the result size limit]",
+            what,
+            format_size(size)
+        ),
+    }
+}
+
+/// Cuts `s` to at most `max` bytes on a character boundary.
+fn truncate_str(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b >= KB * KB {
+        format!("{:.1} MB", b / (KB * KB))
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Writes content to the cache directory and returns its `file://` URI.
+fn store(dir: &Path, bytes: &[u8], mime: &str) -> Result<String, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    prune_cache(dir);
+
+    let digest = Sha256::digest(bytes);
+    let name: String = digest
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let path = dir.join(format!("{}.{}", name, extension_for(mime)));
+    if !path.exists() {
+        std::fs::write(&path, bytes)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    file_uri(&path)
+}
+
+fn prune_cache(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > CACHE_MAX_AGE);
+        if expired {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn file_uri(path: &Path) -> Result<String, String> {
+    url::Url::from_file_path(path)
+        .map(|url| url.to_string())
+        .map_err(|_| format!("Not an absolute path: {}", path.display()))
+}
+
+const MIME_EXTENSIONS: &[(&str, &str)] = &[
+    ("text/plain", "txt"),
+    ("text/markdown", "md"),
+    ("text/html", "html"),
+    ("text/csv", "csv"),
+    ("application/json", "json"),
+    ("application/pdf", "pdf"),
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/svg+xml", "svg"),
+    ("audio/wav", "wav"),
+    ("audio/mpeg", "mp3"),
+    ("audio/ogg", "ogg"),
+];
+
+fn extension_for(mime: &str) -> &'static str {
+    MIME_EXTENSIONS
+        .iter()
+        .find(|(m, _)| *m == mime)
+        .map(|(_, ext)| *ext)
+        .unwrap_or(if mime.starts_with("text/") {
+            "txt"
+        } else {
+            "bin"
+        })
+}
+
+fn mime_for(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    MIME_EXTENSIONS
+        .iter()
+        .find(|(_, e)| *e == ext)
+        .map(|(m, _)| *m)
+        .unwrap_or("application/octet-stream")
+}
+
+/// Reads a spilled result back as a resource contents entry.
+///
+/// Only files inside the cache directory can be read.
+pub fn read_cached(uri: &str) -> Result<Value, String> {
+    read_cached_in(&cache_dir(), uri)
+}
+
+fn read_cached_in(dir: &Path, uri: &str) -> Result<Value, String> {
+    let raw = url::Url::parse(uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| format!("Not a cached result URI: {}", uri))?;
+
+    let path = std::fs::canonicalize(raw).map_err(|e| format!("Cached result not found: {}", e))?;
+    let dir =
+        std::fs::canonicalize(dir).map_err(|e| format!("Cache directory not found: {}", e))?;
+    if !path.starts_with(&dir) {
+        return Err(format!("Not a cached result URI: {}", uri));
+    }
+
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read cached result: {}", e))?;
+    let mime = mime_for(&path);
+    let mut entry = Map::new();
+    entry.insert("uri".to_string(), Value::String(uri.to_string()));
+    entry.insert("mimeType".to_string(), Value::String(mime.to_string()));
+    match String::from_utf8(bytes) {
+        Ok(text) if mime.starts_with("text/") || mime.contains("json") || mime.contains("svg") => {
+            entry.insert("text".to_string(), Value::String(text));
+        }
+        Ok(text) => {
+            entry.insert(
+                "blob".to_string(),
+                Value::String(BASE64.encode(text.as_bytes())),
+            );
+        }
+        Err(e) => {
+            entry.insert(
+                "blob".to_string(),
+                Value::String(BASE64.encode(e.as_bytes())),
+            );
+        }
+    }
+    Ok(json!({ "contents": [Value::Object(entry)] }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ResultLimits {
+        ResultLimits {
+            max_block_size: 100,
+            max_result_size: 150,
+            preview_size: 10,
+            spill: true,
+        }
+    }
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("mcp-content-test-{}", std::process::id()))
+            .join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_small_blocks_pass_through() {
+        let result = json!({"content": [
+            {"type": "text", "text": "hello"},
+            {"type": "image", "data": "aGk=", "mimeType": "image/png"},
+            {"type": "resource", "resource": {"uri": "file:///a", "mimeType": "text/plain", "text": "x"}}
+        ]});
+        assert_eq!(
+            limit_tool_result_in(result.clone(), &limits(), None),
+            result
+        );
+    }
+
+    #[test]
+    fn test_spill_large_text() {
+        let dir = temp_cache("text");
+        let text = "é".repeat(100);
+        let result = limit_tool_result_in(
+            json!({"content": [{"type": "text", "text": text}]}),
+            &limits(),
+            Some(&dir),
+        );
+        let blocks = result["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 2);
+        let preview = blocks[0]["text"].as_str().unwrap();
+        assert!(preview.starts_with("ééééé\n\n[Truncated: 200 B total"));
+        assert_eq!(blocks[1]["type"], "resource_link");
+        assert_eq!(blocks[1]["mimeType"], "text/plain");
+
+        let read = read_cached_in(&dir, blocks[1]["uri"].as_str().unwrap()).unwrap();
+        assert_eq!(read["contents"][0]["text"], text.as_str());
+        assert!(read_cached_in(&dir, "file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_uri_is_percent_encoded() {
+        let dir = temp_cache("dir with space é");
+        let uri = store(&dir, b"hello", "text/plain").unwrap();
+        assert!(uri.starts_with("file://"));
+        assert!(uri.contains("dir%20with%20space%20%C3%A9/"));
+
+        let read = read_cached_in(&dir, &uri).unwrap();
+        assert_eq!(read["contents"][0]["text"], "hello");
+    }
+
+    #[test]
+    fn test_large_binary_and_budget() {
+        let dir = temp_cache("binary");
+        let data = BASE64.encode([7u8; 90]); // 120 chars
+        let result = limit_tool_result_in(
+            json!({"content": [
+                {"type": "text", "text": "a".repeat(80)},
+                {"type": "image", "data": data, "mimeType": "image/png"}
+            ]}),
+            &limits(),
+            Some(&dir),
+        );
+        let blocks = result["content"].as_array().unwrap();
+        // First block fits; the image exceeds the rest of the result budget and is spilled as a .png
+        assert_eq!(blocks[0]["text"].as_str().unwrap().len(), 80);
+        assert!(blocks[1]["text"]
+            .as_str()
+            .unwrap()
+            .contains("image/png (90 B) saved to"));
+        assert!(blocks[2]["uri"].as_str().unwrap().ends_with(".png"));
+
+        // Without spilling the data is dropped with a note
+        let result = limit_tool_result_in(
+            json!({"content": [{"type": "audio", "data": BASE64.encode([0u8; 200]), "mimeType": "audio/wav"}]}),
+            &limits(),
+            None,
+        );
+        assert_eq!(result["content"].as_array().unwrap().len(), 1);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("omitted"));
+    }
+
+    #[test]
+    fn test_media_only_limited_by_result_size() {
+        let image = BASE64.encode([7u8; 105]); // 140 chars, over the block limit
+        let result = json!({"content": [
+            {"type": "image", "data": image, "mimeType": "image/png"}
+        ]});
+        assert_eq!(
+            limit_tool_result_in(result.clone(), &limits(), None),
+            result
+        );
+
+        let result = json!({"contents": [
+            {"uri": "file:///a.wav", "mimeType": "audio/wav", "blob": image}
+        ]});
+        assert_eq!(
+            limit_resource_result_in(result.clone(), &limits(), None),
+            result
+        );
+
+        // Over the result size it is still replaced
+        let result = limit_tool_result_in(
+            json!({"content": [{"type": "image", "data": BASE64.encode([7u8; 150]), "mimeType": "image/png"}]}),
+            &limits(),
+            None,
+        );
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("omitted"));
+    }
+
+    #[test]
+    fn test_resource_contents() {
+        let result = limit_resource_result_in(
+            json!({"contents": [
+                {"uri": "file:///big.txt", "mimeType": "text/plain", "text": "x".repeat(500)},
+                {"uri": "file:///small.png", "mimeType": "image/png", "blob": "aGk="}
+            ]}),
+            &limits(),
+            None,
+        );
+        let text = result["contents"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("xxxxxxxxxx\n\n[Truncated: 500 B total"));
+        assert_eq!(result["contents"][1]["blob"], "aGk=");
+        assert_eq!(result["contents"][1]["mimeType"], "image/png");
+    }
+}
@@ -0,0 +1,278 @@
+//! Variable and path expansion for MCP server configs.
+//!
+//! Applied to `command`, `args`, `env`, `cwd` and sandbox path values right
+//! before a server is spawned, so secrets can stay in the environment instead of the config file.
+//!
+//! ## Syntax
+//! - `~` / `~/path` at the start of a value - home directory
+//! - `${HOME}` - home directory
+//! - `${XDG_CONFIG_HOME}`, `${XDG_DATA_HOME}`, `${XDG_CACHE_HOME}`,
+//!   `${XDG_STATE_HOME}`, `${XDG_RUNTIME_DIR}` - XDG base directories
+//!   (environment value if set, platform default otherwise)
+//! - `${env:NAME}` or `${NAME}` - environment variable (the app's own, or the
+//!   login shell's when the app was started from a desktop menu)
+//! - `${env:NAME:-default}` / `${NAME:-default}` - with a fallback value
+//!   (the fallback is expanded too)
+//! - `$$` - a literal `$`
+//!
+//! A reference that cannot be resolved and has no default is an error.
+
+use std::collections::HashMap;
+
+use super::config::{McpServerConfig, SandboxConfig};
+
+/// Expands `command`, `args`, `env`, `cwd` and sandbox paths of a server config.
+///
+/// All unresolved references are collected and reported together.
+pub fn expand_config(config: &McpServerConfig) -> Result<McpServerConfig, String> {
+    expand_config_with(config, &lookup_var)
+}
+
+fn expand_config_with(
+    config: &McpServerConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<McpServerConfig, String> {
+    let mut unresolved = Vec::new();
+
+    let command = expand_collect(&config.command, lookup, &mut unresolved);
+    let args = config
+        .args
+        .iter()
+        .map(|arg| expand_collect(arg, lookup, &mut unresolved))
+        .collect();
+    let env: HashMap<String, String> = config
+        .env
+        .iter()
+        .map(|(key, value)| (key.clone(), expand_collect(value, lookup, &mut unresolved)))
+        .collect();
+    let cwd = config
+        .cwd
+        .as_ref()
+        .map(|cwd| expand_collect(cwd, lookup, &mut unresolved));
+    let sandbox = config.sandbox.as_ref().map(|sandbox| {
+        let mut expand_paths = |paths: &[String]| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| expand_collect(path, lookup, &mut unresolved))
+                .collect()
+        };
+        SandboxConfig {
+            read: expand_paths(&sandbox.read),
+            write: expand_paths(&sandbox.write),
+            network: sandbox.network,
+        }
+    });
+
+    if !unresolved.is_empty() {
+        unresolved.sort();
+        unresolved.dedup();
+        return Err(format!("unresolved reference(s): {}", unresolved.join(", ")));
+    }
+
+    Ok(McpServerConfig {
+        command,
+        args,
+        env,
+        cwd,
+        sandbox,
+        ..config.clone()
+    })
+}
+
+fn expand_collect(
+    input: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    unresolved: &mut Vec<String>,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    // Leading ~ (only "~" alone or "~/...", not "~user")
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        match lookup("HOME") {
+            Some(home) => {
+                output.push_str(&home);
+                rest = &rest[1..];
+            }
+            None => {
+                unresolved.push("~".to_string());
+                return input.to_string();
+            }
+        }
+    }
+
+    while let Some(pos) = rest.find('$') {
+        output.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        if let Some(stripped) = after.strip_prefix('$') {
+            output.push('$');
+            rest = stripped;
+            continue;
+        }
+
+        if !after.starts_with('{') {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+
+        // Find the matching closing brace (defaults may contain nested references)
+        let body_start = 1;
+        let mut depth = 1;
+        let mut end = None;
+        for (i, c) in after[body_start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(body_start + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(end) = end else {
+            // Unterminated reference
+            unresolved.push(format!("${}", after));
+            output.push('$');
+            output.push_str(after);
+            return output;
+        };
+
+        let body = &after[body_start..end];
+        let (name, default) = match body.find(":-") {
+            Some(i) => (&body[..i], Some(&body[i + 2..])),
+            None => (body, None),
+        };
+        let name = name.strip_prefix("env:").unwrap_or(name);
+
+        let value = if is_valid_name(name) {
+            lookup(name)
+        } else {
+            None
+        };
+
+        match (value, default) {
+            (Some(value), _) => output.push_str(&value),
+            (None, Some(default)) => {
+                output.push_str(&expand_collect(default, lookup, unresolved));
+            }
+            (None, None) => {
+                unresolved.push(format!("${{{}}}", body));
+                output.push_str(&after[..=end]);
+            }
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Resolves a variable name: home and XDG directories first, then the process
+/// environment, then the login shell's environment.
+fn lookup_var(name: &str) -> Option<String> {
+    let dir = match name {
+        "HOME" => dirs::home_dir(),
+        "XDG_CONFIG_HOME" => env_or(name, dirs::config_dir),
+        "XDG_DATA_HOME" => env_or(name, dirs::data_dir),
+        "XDG_CACHE_HOME" => env_or(name, dirs::cache_dir),
+        "XDG_STATE_HOME" => env_or(name, dirs::state_dir),
+        "XDG_RUNTIME_DIR" => env_or(name, dirs::runtime_dir),
+        _ => {
+            return std::env::var(name)
+                .ok()
+                .or_else(|| super::shell_env::login_var(name))
+        }
+    };
+    dir.map(|p| p.to_string_lossy().to_string())
+}
+
+fn env_or(
+    name: &str,
+    fallback: fn() -> Option<std::path::PathBuf>,
+) -> Option<std::path::PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(fallback)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/user".to_string()),
+            "TOKEN" => Some("secret".to_string()),
+            _ => None,
+        }
+    }
+
+    fn run(input: &str) -> Result<String, Vec<String>> {
+        let mut unresolved = Vec::new();
+        let output = expand_collect(input, &lookup, &mut unresolved);
+        if unresolved.is_empty() {
+            Ok(output)
+        } else {
+            Err(unresolved)
+        }
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(run("~").unwrap(), "/home/user");
+        assert_eq!(run("~/projects").unwrap(), "/home/user/projects");
+        assert_eq!(run("${HOME}/x").unwrap(), "/home/user/x");
+        // Only a leading tilde is expanded
+        assert_eq!(run("a~/b").unwrap(), "a~/b");
+        assert_eq!(run("~other").unwrap(), "~other");
+    }
+
+    #[test]
+    fn test_expand_env() {
+        assert_eq!(run("${env:TOKEN}").unwrap(), "secret");
+        assert_eq!(run("Bearer ${TOKEN}!").unwrap(), "Bearer secret!");
+        assert_eq!(run("${env:MISSING:-fallback}").unwrap(), "fallback");
+        assert_eq!(run("${MISSING:-${HOME}/d}").unwrap(), "/home/user/d");
+        assert_eq!(run("${MISSING:-}").unwrap(), "");
+    }
+
+    #[test]
+    fn test_expand_literals() {
+        assert_eq!(run("$$HOME").unwrap(), "$HOME");
+        assert_eq!(run("cost: $5").unwrap(), "cost: $5");
+        assert_eq!(run("no refs").unwrap(), "no refs");
+    }
+
+    #[test]
+    fn test_expand_unresolved() {
+        assert_eq!(run("${env:MISSING}").unwrap_err(), vec!["${env:MISSING}"]);
+        assert_eq!(run("${user_config.key}").unwrap_err(), vec!["${user_config.key}"]);
+        assert!(run("${HOME").is_err());
+    }
+
+    #[test]
+    fn test_expand_config_reports_all() {
+        let config = McpServerConfig {
+            command: "~/bin/server".to_string(),
+            args: vec!["${A}".to_string(), "${TOKEN}".to_string()],
+            env: HashMap::from([("KEY".to_string(), "${env:B}".to_string())]),
+            ..Default::default()
+        };
+        let err = expand_config_with(&config, &lookup).unwrap_err();
+        assert_eq!(err, "unresolved reference(s): ${A}, ${env:B}");
+    }
+}
@@ -0,0 +1,420 @@
+//! Splits a server's stdout into JSON-RPC messages.
+//!
+//! Servers are supposed to write one JSON object per line, but some
+//! pretty-print multiline JSON, send batch arrays, or mix log lines into
+//! stdout. Instead of reading lines, the stream is scanned for balanced
+//! top-level `{...}` / `[...]` values:
+//!
+//! - Whitespace between messages is ignored; any other text outside a
+//!   message is skipped up to the end of its line.
+//! - A log line that merely starts with `{` or `[` (`[INFO] waiting {`) is
+//!   caught at a line break, where the buffered text must still be the start
+//!   of valid JSON. Otherwise its first line is skipped as noise and the rest
+//!   is scanned again. An invalid balanced value followed by more text on its
+//!   line (`[INFO] ready`) is noise as well.
+//! - A message larger than the size limit is not buffered. The rest of it is
+//!   scanned without being stored, and its top-level `id` is picked up on the
+//!   way so the affected request can be failed.
+
+use serde::de::IgnoredAny;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+
+use super::jsonrpc::Id;
+
+/// Default limit for a single inbound message
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Bytes of skipped non-JSON output kept for logging
+const NOISE_PREVIEW: usize = 200;
+/// Longest `id` value that is captured from an oversized message
+const MAX_ID_LEN: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    /// A complete JSON value (object or batch array)
+    Message(Value),
+    /// A message exceeded the size limit and was discarded
+    Oversized { id: Option<Id>, size: usize },
+    /// A balanced value that is not valid JSON
+    Malformed(String),
+    /// Text outside any message (logged by the caller)
+    Noise(String),
+}
+
+pub struct MessageReader<R: Read> {
+    reader: BufReader<R>,
+    max_size: usize,
+    /// Bytes to scan again after a noise line was split off a buffered value
+    pending: Vec<u8>,
+}
+
+/// Scanner state for the value currently being read
+#[derive(Default)]
+struct Scan {
+    buf: Vec<u8>,
+    size: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    oversized: bool,
+    /// Buffer length at the last valid-prefix check
+    checked: usize,
+    // Top-level key tracking, used to find `id` in oversized messages
+    key: Vec<u8>,
+    id_raw: Option<Vec<u8>>,
+    capturing_id: bool,
+}
+
+impl<R: Read> MessageReader<R> {
+    pub fn new(inner: R, max_size: usize) -> Self {
+        Self {
+            reader: BufReader::with_capacity(64 * 1024, inner),
+            max_size,
+            pending: Vec::new(),
+        }
+    }
+
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pending.is_empty() {
+            self.reader.fill_buf()
+        } else {
+            Ok(&self.pending)
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if self.pending.is_empty() {
+            self.reader.consume(amount);
+        } else {
+            self.pending.drain(..amount);
+        }
+    }
+
+    /// Returns the next frame, or `None` at end of stream.
+    pub fn next_frame(&mut self) -> std::io::Result<Option<Frame>> {
+        // Skip whitespace and noise until a value starts
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+            let first = available[0];
+            if first.is_ascii_whitespace() {
+                self.consume(1);
+                continue;
+            }
+            if first == b'{' || first == b'[' {
+                break;
+            }
+            return self.skip_line().map(|noise| Some(Frame::Noise(noise)));
+        }
+
+        let mut scan = Scan::default();
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                // Stream ended inside a message
+                return Ok(Some(if scan.oversized {
+                    Frame::Oversized {
+                        id: scan.id(),
+                        size: scan.size,
+                    }
+                } else {
+                    Frame::Malformed(format!(
+                        "stream ended inside a message ({} bytes)",
+                        scan.size
+                    ))
+                }));
+            }
+
+            let mut consumed = 0;
+            let mut complete = false;
+            let mut line_end = false;
+            for &byte in available {
+                consumed += 1;
+                if scan.push(byte) {
+                    complete = true;
+                    break;
+                }
+                if byte == b'\n' {
+                    line_end = true;
+                    break;
+                }
+            }
+
+            if !scan.oversized {
+                scan.buf.extend_from_slice(&available[..consumed]);
+                if scan.buf.len() > self.max_size {
+                    scan.oversized = true;
+                    scan.buf = Vec::new();
+                }
+            }
+            self.consume(consumed);
+
+            if complete {
+                let frame = scan.finish();
+                // A balanced but invalid value with more text on its line is a log line (`[INFO] ...`)
+                if let Frame::Malformed(_) = frame {
+                    let next = self.fill_buf()?.first().copied();
+                    if next.is_some_and(|b| b != b'\n' && b != b'\r') {
+                        let rest = self.skip_line()?;
+                        let mut noise = scan.buf;
+                        noise.truncate(NOISE_PREVIEW);
+                        let noise = format!("{}{}", String::from_utf8_lossy(&noise), rest);
+                        return Ok(Some(Frame::Noise(noise)));
+                    }
+                }
+                return Ok(Some(frame));
+            }
+            if line_end && !scan.is_json_prefix() {
+                return Ok(Some(self.split_noise(scan.buf)));
+            }
+        }
+    }
+
+    /// Returns the first line of a buffered non-JSON value as noise and
+    /// queues the rest to be scanned again.
+    fn split_noise(&mut self, mut buf: Vec<u8>) -> Frame {
+        let line_len = buf
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buf.len(), |i| i + 1);
+        let rest = buf.split_off(line_len);
+        let mut pending = std::mem::take(&mut self.pending);
+        self.pending = rest;
+        self.pending.append(&mut pending);
+        buf.truncate(NOISE_PREVIEW);
+        Frame::Noise(String::from_utf8_lossy(&buf).trim_end().to_string())
+    }
+
+    fn skip_line(&mut self) -> std::io::Result<String> {
+        let mut preview = Vec::new();
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (&available[..=i], true),
+                None => (available, false),
+            };
+            let room = NOISE_PREVIEW.saturating_sub(preview.len());
+            preview.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            let len = chunk.len();
+            self.consume(len);
+            if done {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&preview).trim_end().to_string())
+    }
+}
+
+impl Scan {
+    /// Feeds one byte; returns true when the value is complete.
+    fn push(&mut self, byte: u8) -> bool {
+        self.size += 1;
+
+        if self.in_string {
+            let closing = !self.escaped && byte == b'"';
+            self.escaped = !self.escaped && byte == b'\\';
+            if self.capturing_id {
+                self.capture(byte);
+            } else if self.depth == 1 && !closing && self.key.len() < 8 {
+                self.key.push(byte);
+            }
+            if closing {
+                self.in_string = false;
+            }
+            return false;
+        }
+
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                if self.capturing_id {
+                    self.capture(byte);
+                } else if self.depth == 1 {
+                    self.key.clear();
+                }
+            }
+            b'{' | b'[' => {
+                self.depth += 1;
+                if self.capturing_id {
+                    self.capture(byte);
+                }
+            }
+            b'}' | b']' => {
+                if self.depth == 1 {
+                    self.capturing_id = false;
+                } else if self.capturing_id {
+                    self.capture(byte);
+                }
+                self.depth = self.depth.saturating_sub(1);
+                return self.depth == 0;
+            }
+            b':' if self.depth == 1 && !self.capturing_id => {
+                let key = std::mem::take(&mut self.key);
+                if key == b"id" && self.id_raw.is_none() {
+                    self.capturing_id = true;
+                    self.id_raw = Some(Vec::new());
+                }
+            }
+            b',' if self.depth == 1 => {
+                self.capturing_id = false;
+                self.key.clear();
+            }
+            _ => {
+                if self.capturing_id {
+                    self.capture(byte);
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether the buffered bytes can still become valid JSON.
+    ///
+    /// Checked at line breaks, but only once the buffer has doubled since the
+    /// last check, so long pretty-printed messages are not parsed over and over.
+    fn is_json_prefix(&mut self) -> bool {
+        if self.oversized || self.buf.len() < self.checked * 2 {
+            return true;
+        }
+        self.checked = self.buf.len();
+        match serde_json::from_slice::<IgnoredAny>(&self.buf) {
+            Err(e) => e.is_eof(),
+            Ok(_) => true,
+        }
+    }
+
+    fn capture(&mut self, byte: u8) {
+        if let Some(raw) = self.id_raw.as_mut() {
+            if raw.len() < MAX_ID_LEN {
+                raw.push(byte);
+            }
+        }
+    }
+
+    fn id(&self) -> Option<Id> {
+        let raw = self.id_raw.as_ref()?;
+        serde_json::from_slice(raw).ok()
+    }
+
+    fn finish(&self) -> Frame {
+        if self.oversized {
+            return Frame::Oversized {
+                id: self.id(),
+                size: self.size,
+            };
+        }
+        match serde_json::from_slice(&self.buf) {
+            Ok(value) => Frame::Message(value),
+            Err(e) => Frame::Malformed(format!(
+                "{} | {}",
+                e,
+                String::from_utf8_lossy(&self.buf[..self.buf.len().min(NOISE_PREVIEW)])
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn frames(input: &str, max_size: usize) -> Vec<Frame> {
+        let mut reader = MessageReader::new(input.as_bytes(), max_size);
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame().unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn test_lines_multiline_and_batches() {
+        let input = concat!(
+            "{\"id\":1,\"result\":{}}\n",
+            "{\n  \"id\": 2,\n  \"result\": {\"text\": \"a } ] \\\" b\"}\n}\n",
+            "[{\"id\":3,\"result\":1},{\"method\":\"n\"}]",
+            "{\"id\":4,\"result\":[]}"
+        );
+        assert_eq!(
+            frames(input, 1024),
+            vec![
+                Frame::Message(json!({"id": 1, "result": {}})),
+                Frame::Message(json!({"id": 2, "result": {"text": "a } ] \" b"}})),
+                Frame::Message(json!([{"id": 3, "result": 1}, {"method": "n"}])),
+                Frame::Message(json!({"id": 4, "result": []})),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_noise_is_skipped() {
+        let input = "Server starting on stdio...\n{\"id\":1,\"result\":true}\nnot json }\n";
+        assert_eq!(
+            frames(input, 1024),
+            vec![
+                Frame::Noise("Server starting on stdio...".to_string()),
+                Frame::Message(json!({"id": 1, "result": true})),
+                Frame::Noise("not json }".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_noise_line_is_skipped() {
+        let input = concat!(
+            "[INFO] waiting {\n",
+            "{\"id\":1,\"result\":true}\n",
+            "{\n",
+            "{\"id\":2,\"result\":false}\n",
+            "{\n  \"id\": 3,\n  \"result\": null\n}\n"
+        );
+        assert_eq!(
+            frames(input, 1024),
+            vec![
+                Frame::Noise("[INFO] waiting {".to_string()),
+                Frame::Message(json!({"id": 1, "result": true})),
+                Frame::Noise("{".to_string()),
+                Frame::Message(json!({"id": 2, "result": false})),
+                Frame::Message(json!({"id": 3, "result": null})),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oversized_message_reports_id() {
+        let big = "x".repeat(5000);
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"result\":{{\"text\":\"{}\",\"id\":99}},\"id\":\"req-7\"}}\n{{\"id\":8,\"result\":null}}",
+            big
+        );
+        let frames = frames(&input, 1000);
+        assert_eq!(
+            frames[0],
+            Frame::Oversized {
+                id: Some(Id::String("req-7".to_string())),
+                size: input.find('\n').unwrap()
+            }
+        );
+        // The reader keeps going after an oversized message
+        assert_eq!(frames[1], Frame::Message(json!({"id": 8, "result": null})));
+    }
+
+    #[test]
+    fn test_malformed_and_truncated() {
+        assert!(matches!(
+            frames("{\"id\": 1,,}", 1024)[0],
+            Frame::Malformed(_)
+        ));
+        assert!(matches!(
+            frames("{\"id\": 1, \"result\": ", 1024)[0],
+            Frame::Malformed(_)
+        ));
+    }
+}
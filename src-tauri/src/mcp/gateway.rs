@@ -0,0 +1,561 @@
+//! Local MCP gateway.
+//!
+//! With `gateway.enabled`, the app serves the tools and resources of all its
+//! servers as a single MCP server on a Unix socket, so terminal tools and IDEs
+//! reuse the running servers, their extension `user_config` and the
+//! permission policy instead of configuring them again. Clients connect
+//! through the stdio shim `claude-desktop mcp serve`.
+//!
+//! Messages are newline-delimited JSON-RPC, as on MCP stdio. The first line of
+//! a connection must be `{"token": "..."}` with the token the app writes to
+//! `mcp-gateway.token` (readable only by the user) when the gateway starts.
+//!
+//! Tools are offered under their exposed names (`serverSettings` prefixes and
+//! aliases). If two servers expose the same name, the first server in name
+//! order gets the call.
+
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, warn};
+
+use super::client::McpTool;
+use super::config::{GatewayConfig, McpConfig};
+use super::jsonrpc::{ErrorObject, INTERNAL_ERROR, METHOD_NOT_FOUND};
+use super::manager::{McpManager, ServerInfo, ServerStatus};
+use crate::error::AppError;
+
+/// Protocol versions the gateway accepts; the last one is offered otherwise
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const INVALID_PARAMS: i64 = -32602;
+/// Request timeout, as used by the MCP SDKs
+const REQUEST_TIMEOUT: i64 = -32001;
+/// Longest token line read from a connection that is not authorized yet
+const MAX_AUTH_LINE: u64 = 1024;
+
+pub fn socket_path(config: &GatewayConfig) -> PathBuf {
+    match &config.socket_path {
+        Some(path) => PathBuf::from(path),
+        None => config_dir().join("mcp-gateway.sock"),
+    }
+}
+
+pub fn token_path() -> PathBuf {
+    config_dir().join("mcp-gateway.token")
+}
+
+fn config_dir() -> PathBuf {
+    McpConfig::config_path()
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// The first line a client sends on a gateway connection.
+pub fn auth_line(token: &str) -> String {
+    format!("{}\n", json!({ "token": token }))
+}
+
+/// Starts the gateway if it is enabled in the config.
+#[cfg(unix)]
+pub fn start(manager: Arc<RwLock<McpManager>>) {
+    let config = McpConfig::load().map(|c| c.gateway).unwrap_or_default();
+    if !config.enabled {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(&socket_path(&config), manager).await {
+            warn!("MCP gateway is not available: {}", e);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn start(_manager: Arc<RwLock<McpManager>>) {
+    if McpConfig::load().is_ok_and(|c| c.gateway.enabled) {
+        warn!("The MCP gateway is only supported on Linux and macOS");
+    }
+}
+
+#[cfg(unix)]
+async fn listen(path: &std::path::Path, manager: Arc<RwLock<McpManager>>) -> Result<(), String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use tokio::net::{UnixListener, UnixStream};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        // socketPath may point at anything; never delete what is not a socket
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket", path.display()));
+        }
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(format!("{} is in use by another process", path.display()));
+        }
+        // Left over from a previous run
+        let _ = std::fs::remove_file(path);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let listener =
+        UnixListener::bind(path).map_err(|e| format!("cannot bind {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| e.to_string())?;
+    let token = Arc::new(write_token()?);
+    tracing::info!("MCP gateway listening on {}", path.display());
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("MCP gateway accept failed: {}", e);
+                continue;
+            }
+        };
+        let manager = manager.clone();
+        let token = token.clone();
+        tauri::async_runtime::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = tokio::io::BufReader::new(reader);
+            let mut line = String::new();
+            let authorized = (&mut reader)
+                .take(MAX_AUTH_LINE)
+                .read_line(&mut line)
+                .await
+                .is_ok()
+                && serde_json::from_str::<Value>(&line)
+                    .ok()
+                    .and_then(|v| v.get("token").and_then(Value::as_str).map(str::to_string))
+                    .is_some_and(|t| t == *token);
+            if !authorized {
+                debug!("MCP gateway connection rejected: invalid token");
+                let response = error_response(
+                    Value::Null,
+                    ErrorObject {
+                        code: INVALID_REQUEST,
+                        message: "Invalid gateway token".to_string(),
+                        data: None,
+                    },
+                );
+                let _ = writer.write_all(format!("{}\n", response).as_bytes()).await;
+                return;
+            }
+            debug!("MCP gateway client connected");
+            serve(reader, writer, manager).await;
+            debug!("MCP gateway client disconnected");
+        });
+    }
+}
+
+/// Writes a new random token that only the user can read.
+#[cfg(unix)]
+fn write_token() -> Result<String, String> {
+    use std::io::{Read, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut bytes = [0u8; 32];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .map_err(|e| format!("cannot generate token: {}", e))?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let path = token_path();
+    let _ = std::fs::remove_file(&path);
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut f| f.write_all(token.as_bytes()))
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// Serves MCP on a connection until the client closes it. Requests are
+/// handled concurrently; responses are written in the order they complete.
+pub async fn serve<R, W>(mut reader: R, mut writer: W, manager: Arc<RwLock<McpManager>>)
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let write_task = tauri::async_runtime::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let line = format!("{}\n", message);
+            if writer.write_all(line.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = ErrorObject {
+                    code: PARSE_ERROR,
+                    message: format!("Parse error: {}", e),
+                    data: None,
+                };
+                let _ = tx.send(error_response(Value::Null, error));
+                continue;
+            }
+        };
+        let manager = manager.clone();
+        let tx = tx.clone();
+        tauri::async_runtime::spawn(async move {
+            let manager = manager.read().await;
+            if let Some(response) = handle_message(&manager, &message).await {
+                let _ = tx.send(response);
+            }
+        });
+    }
+
+    // Responses to requests still running are written before returning
+    drop(tx);
+    let _ = write_task.await;
+}
+
+/// Handles one message from a client. Returns the response to a request, or
+/// `None` for notifications.
+pub async fn handle_message(manager: &McpManager, message: &Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        // The gateway sends no requests, so responses from the client are dropped
+        if message.get("result").is_some() || message.get("error").is_some() {
+            return None;
+        }
+        let error = ErrorObject {
+            code: INVALID_REQUEST,
+            message: "Invalid request".to_string(),
+            data: None,
+        };
+        return Some(error_response(id.unwrap_or(Value::Null), error));
+    };
+    let id = id?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    debug!("MCP gateway request: method={}", method);
+    match handle_request(manager, method, &params).await {
+        Ok(result) => Some(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+        Err(error) => Some(error_response(id, error)),
+    }
+}
+
+async fn handle_request(
+    manager: &McpManager,
+    method: &str,
+    params: &Value,
+) -> Result<Value, ErrorObject> {
+    match method {
+        "initialize" => Ok(initialize_result(params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => {
+            let servers = offered_servers(manager, true).await;
+            let tools: Vec<Value> = unique_tools(&servers)
+                .into_iter()
+                .map(|(_, tool)| tool_entry(tool))
+                .collect();
+            Ok(json!({ "tools": tools }))
+        }
+        "tools/call" => {
+            let name = params
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid_params("Missing tool name"))?;
+            let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+            let route = |servers: &[ServerInfo]| {
+                unique_tools(servers)
+                    .into_iter()
+                    .find(|(_, tool)| tool.name == name)
+                    .map(|(server, _)| server.to_string())
+            };
+            let server = match route(&offered_servers(manager, false).await) {
+                Some(server) => server,
+                None => route(&offered_servers(manager, true).await)
+                    .ok_or_else(|| invalid_params(&format!("Unknown tool: {}", name)))?,
+            };
+            match manager.call_tool(&server, name, arguments).await {
+                Ok(result) => Ok(result),
+                Err(e) => call_failure(e),
+            }
+        }
+        "resources/list" => {
+            let servers = offered_servers(manager, true).await;
+            let resources: Vec<&super::client::McpResource> =
+                servers.iter().flat_map(|s| &s.resources).collect();
+            Ok(json!({ "resources": resources }))
+        }
+        "resources/read" => {
+            let uri = params
+                .get("uri")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid_params("Missing resource uri"))?;
+            let route = |servers: Vec<ServerInfo>| {
+                servers
+                    .into_iter()
+                    .find(|s| s.resources.iter().any(|r| r.uri == uri))
+                    .map(|s| s.name)
+            };
+            let server = match route(offered_servers(manager, false).await) {
+                Some(server) => server,
+                None => route(offered_servers(manager, true).await)
+                    .ok_or_else(|| invalid_params(&format!("Unknown resource: {}", uri)))?,
+            };
+            manager.read_resource(&server, uri).await.map_err(rpc_error)
+        }
+        _ => Err(ErrorObject {
+            code: METHOD_NOT_FOUND,
+            message: format!("Method not found: {}", method),
+            data: None,
+        }),
+    }
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[PROTOCOL_VERSIONS.len() - 1]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {}, "resources": {} },
+        "serverInfo": { "name": "claude-desktop", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+/// Servers whose tools and resources are offered, in name order.
+///
+/// Idle servers are listed with their cached catalog. With `start`, those
+/// without one are started to read their tools; calls first try to find their
+/// server without starting any.
+async fn offered_servers(manager: &McpManager, start: bool) -> Vec<ServerInfo> {
+    let usable =
+        |s: &ServerInfo| !matches!(s.status, ServerStatus::Disabled | ServerStatus::Untrusted);
+    let mut servers = manager.list_servers().await;
+    let mut started = false;
+    for server in servers
+        .iter()
+        .filter(|s| start && usable(s) && needs_start(s))
+    {
+        match manager.list_tools(&server.name).await {
+            Ok(_) => started = true,
+            Err(e) => warn!("MCP gateway: cannot list tools of {}: {}", server.name, e),
+        }
+    }
+    if started {
+        servers = manager.list_servers().await;
+    }
+    servers.retain(usable);
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    servers
+}
+
+fn needs_start(server: &ServerInfo) -> bool {
+    !matches!(
+        server.status,
+        ServerStatus::Running | ServerStatus::Unhealthy
+    ) && !server.stale
+}
+
+/// Tools by exposed name with their server; the first server wins a name.
+fn unique_tools(servers: &[ServerInfo]) -> Vec<(&str, &McpTool)> {
+    let mut seen = std::collections::HashSet::new();
+    servers
+        .iter()
+        .flat_map(|s| s.tools.iter().map(move |t| (s.name.as_str(), t)))
+        .filter(|(_, tool)| seen.insert(tool.name.as_str()))
+        .collect()
+}
+
+/// A tool in the MCP `tools/list` format
+fn tool_entry(tool: &McpTool) -> Value {
+    let mut entry = json!({
+        "name": tool.name,
+        "inputSchema": if tool.input_schema.is_null() {
+            json!({ "type": "object" })
+        } else {
+            tool.input_schema.clone()
+        },
+    });
+    let fields = [
+        ("title", tool.title.as_ref().map(|t| json!(t))),
+        ("description", tool.description.as_ref().map(|d| json!(d))),
+        ("outputSchema", tool.output_schema.clone()),
+        (
+            "annotations",
+            tool.annotations
+                .as_ref()
+                .and_then(|a| serde_json::to_value(a).ok()),
+        ),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            entry[key] = value;
+        }
+    }
+    entry
+}
+
+/// Denied tool calls are returned as a tool result with `isError`, so the
+/// model sees why; other failures become JSON-RPC errors.
+fn call_failure(error: AppError) -> Result<Value, ErrorObject> {
+    if error.code() == "MCP_PERMISSION_DENIED" {
+        return Ok(json!({
+            "content": [{ "type": "text", "text": error.to_string() }],
+            "isError": true
+        }));
+    }
+    Err(rpc_error(error))
+}
+
+fn rpc_error(error: AppError) -> ErrorObject {
+    let code = error.code();
+    ErrorObject {
+        code: match code {
+            "MCP_TIMEOUT" => REQUEST_TIMEOUT,
+            "MCP_TOOL_UNAVAILABLE" => INVALID_PARAMS,
+            _ => INTERNAL_ERROR,
+        },
+        message: error.to_string(),
+        data: Some(match error.data() {
+            Some(data) => json!({ "code": code, "data": data }),
+            None => json!({ "code": code }),
+        }),
+    }
+}
+
+fn invalid_params(message: &str) -> ErrorObject {
+    ErrorObject {
+        code: INVALID_PARAMS,
+        message: message.to_string(),
+        data: None,
+    }
+}
+
+fn error_response(id: Value, error: ErrorObject) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::McpError;
+
+    fn server(name: &str, tools: &[&str]) -> ServerInfo {
+        ServerInfo {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            status: ServerStatus::Running,
+            tools: tools
+                .iter()
+                .map(|t| McpTool {
+                    name: t.to_string(),
+                    title: None,
+                    description: None,
+                    input_schema: Value::Null,
+                    output_schema: None,
+                    annotations: None,
+                })
+                .collect(),
+            resources: Vec::new(),
+            queue: None,
+            stale: false,
+            last_ping_ms: None,
+            rss_bytes: None,
+            cpu_ms: None,
+            exit_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_unique_tools() {
+        let servers = [
+            server("a", &["read", "write"]),
+            server("b", &["read", "list"]),
+        ];
+        let routes: Vec<(&str, &str)> = unique_tools(&servers)
+            .into_iter()
+            .map(|(server, tool)| (server, tool.name.as_str()))
+            .collect();
+        assert_eq!(routes, [("a", "read"), ("a", "write"), ("b", "list")]);
+        assert_eq!(
+            tool_entry(servers[0].tools.first().unwrap()),
+            json!({ "name": "read", "inputSchema": { "type": "object" } })
+        );
+    }
+
+    #[test]
+    fn test_initialize_version() {
+        let version = |params: Value| initialize_result(&params)["protocolVersion"].clone();
+        assert_eq!(
+            version(json!({ "protocolVersion": "2024-11-05" })),
+            "2024-11-05"
+        );
+        assert_eq!(
+            version(json!({ "protocolVersion": "1999-01-01" })),
+            "2025-06-18"
+        );
+        assert_eq!(version(Value::Null), "2025-06-18");
+    }
+
+    #[test]
+    fn test_call_failure() {
+        let denied = call_failure(McpError::PermissionDenied("denied".into()).into()).unwrap();
+        assert_eq!(denied["isError"], true);
+
+        let error = call_failure(McpError::ServerNotFound("x".into()).into()).unwrap_err();
+        assert_eq!(error.code, INTERNAL_ERROR);
+        assert_eq!(error.data, Some(json!({ "code": "MCP_SERVER_NOT_FOUND" })));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_listen_keeps_files_that_are_not_sockets() {
+        let path = std::env::temp_dir().join(format!("mcp-gateway-test-{}", std::process::id()));
+        std::fs::write(&path, "keep").unwrap();
+
+        let manager = Arc::new(RwLock::new(McpManager::new()));
+        let error = listen(&path, manager).await.unwrap_err();
+        assert!(error.contains("is not a socket"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_message() {
+        let manager = McpManager::new();
+        let response = handle_message(
+            &manager,
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"], json!({ "tools": [] }));
+
+        let response = handle_message(
+            &manager,
+            &json!({ "jsonrpc": "2.0", "id": "x", "method": "prompts/list" }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], "x");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert_eq!(handle_message(&manager, &notification).await, None);
+    }
+}
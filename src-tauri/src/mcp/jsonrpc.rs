@@ -0,0 +1,217 @@
+//! JSON-RPC 2.0 message types used by the stdio client.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// JSON-RPC request id: a number or a string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Id {
+    Number(i64),
+    String(String),
+}
+
+impl Id {
+    /// The same id in the other representation (`5` <-> `"5"`).
+    ///
+    /// Some servers echo numeric ids back as strings, so responses are
+    /// matched against both forms.
+    pub fn alternate(&self) -> Option<Id> {
+        match self {
+            Id::Number(n) => Some(Id::String(n.to_string())),
+            Id::String(s) => s.parse().ok().map(Id::Number),
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Id> {
+        match value {
+            Value::Number(n) => n
+                .as_i64()
+                .or_else(|| n.as_u64().and_then(|u| i64::try_from(u).ok()))
+                .map(Id::Number),
+            Value::String(s) => Some(Id::String(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::Number(n) => write!(f, "{}", n),
+            Id::String(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+pub const INTERNAL_ERROR: i64 = -32603;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+impl ErrorObject {
+    /// Parses an `error` member, tolerating servers that send a bare string
+    /// or omit fields.
+    fn from_value(value: &Value) -> ErrorObject {
+        if let Ok(error) = serde_json::from_value::<ErrorObject>(value.clone()) {
+            return error;
+        }
+        match value {
+            Value::String(message) => ErrorObject {
+                code: INTERNAL_ERROR,
+                message: message.clone(),
+                data: None,
+            },
+            _ => ErrorObject {
+                code: value
+                    .get("code")
+                    .and_then(|c| c.as_i64())
+                    .unwrap_or(INTERNAL_ERROR),
+                message: value
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+                data: Some(value.clone()),
+            },
+        }
+    }
+}
+
+/// A classified inbound message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inbound {
+    /// Response to one of our requests
+    Response {
+        id: Id,
+        result: Result<Value, ErrorObject>,
+    },
+    /// Error response with a `null` id (the server could not read the request id)
+    OrphanError(ErrorObject),
+    /// Request from the server to the client
+    Request {
+        id: Id,
+        method: String,
+    },
+    Notification {
+        method: String,
+    },
+    Invalid(String),
+}
+
+pub fn classify(message: &Value) -> Inbound {
+    let Some(obj) = message.as_object() else {
+        return Inbound::Invalid("message is not an object".to_string());
+    };
+
+    if let Some(method) = obj.get("method").and_then(|m| m.as_str()) {
+        return match obj.get("id").and_then(Id::from_value) {
+            Some(id) => Inbound::Request {
+                id,
+                method: method.to_string(),
+            },
+            None => Inbound::Notification {
+                method: method.to_string(),
+            },
+        };
+    }
+
+    let id = obj.get("id").unwrap_or(&Value::Null);
+    let outcome = match (obj.get("error"), obj.get("result")) {
+        (Some(error), _) if !error.is_null() => Err(ErrorObject::from_value(error)),
+        (_, Some(result)) => Ok(result.clone()),
+        _ => return Inbound::Invalid("response has neither result nor error".to_string()),
+    };
+
+    match (Id::from_value(id), outcome) {
+        (Some(id), result) => Inbound::Response { id, result },
+        (None, Err(error)) if id.is_null() => Inbound::OrphanError(error),
+        _ => Inbound::Invalid(format!("response has an invalid id: {}", id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_id_forms() {
+        assert_eq!(serde_json::to_value(Id::Number(3)).unwrap(), json!(3));
+        assert_eq!(
+            serde_json::to_value(Id::String("a".into())).unwrap(),
+            json!("a")
+        );
+        assert_eq!(Id::Number(7).alternate(), Some(Id::String("7".into())));
+        assert_eq!(Id::String("7".into()).alternate(), Some(Id::Number(7)));
+        assert_eq!(Id::String("x".into()).alternate(), None);
+    }
+
+    #[test]
+    fn test_classify_responses() {
+        assert_eq!(
+            classify(&json!({"jsonrpc": "2.0", "id": "4", "result": {"ok": true}})),
+            Inbound::Response {
+                id: Id::String("4".into()),
+                result: Ok(json!({"ok": true}))
+            }
+        );
+
+        let error = json!({"code": -32602, "message": "Invalid params", "data": {"field": "x"}});
+        assert_eq!(
+            classify(&json!({"jsonrpc": "2.0", "id": 2, "error": error})),
+            Inbound::Response {
+                id: Id::Number(2),
+                result: Err(ErrorObject {
+                    code: -32602,
+                    message: "Invalid params".into(),
+                    data: Some(json!({"field": "x"}))
+                })
+            }
+        );
+
+        assert!(matches!(
+            classify(
+                &json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "Parse error"}})
+            ),
+            Inbound::OrphanError(ErrorObject { code: -32700, .. })
+        ));
+        assert!(matches!(
+            classify(&json!({"jsonrpc": "2.0", "id": 1, "error": "boom"})),
+            Inbound::Response {
+                result: Err(ErrorObject {
+                    code: INTERNAL_ERROR,
+                    ..
+                }),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_classify_server_messages() {
+        assert_eq!(
+            classify(&json!({"jsonrpc": "2.0", "id": "r1", "method": "roots/list"})),
+            Inbound::Request {
+                id: Id::String("r1".into()),
+                method: "roots/list".into()
+            }
+        );
+        assert_eq!(
+            classify(&json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"})),
+            Inbound::Notification {
+                method: "notifications/tools/list_changed".into()
+            }
+        );
+        assert!(matches!(classify(&json!([1])), Inbound::Invalid(_)));
+        assert!(matches!(classify(&json!({"id": 1})), Inbound::Invalid(_)));
+    }
+}
@@ -0,0 +1,313 @@
+//! Resource limits and usage of MCP server processes.
+//!
+//! `limits` in a server config are applied with `setrlimit` between fork and
+//! exec, so they also cover processes the server starts (e.g. `npx` running
+//! `node`). Memory and CPU usage are read from `/proc` for the whole process
+//! tree (Linux only).
+//!
+//! A server that dies because of a limit is reported with its own status.
+//! CPU time is recognised by `SIGXCPU`; the other limits make system calls
+//! fail rather than kill the process, so they are recognised by the error the
+//! server printed to stderr before exiting.
+
+use std::collections::HashMap;
+use std::process::{Command, ExitStatus};
+
+use super::config::ResourceLimits;
+
+/// Seconds between the soft CPU limit (`SIGXCPU`) and the hard one (`SIGKILL`)
+#[cfg(unix)]
+const CPU_GRACE_SECS: u64 = 5;
+
+/// Which limit a server ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Memory,
+    Cpu,
+    OpenFiles,
+    Processes,
+}
+
+impl Limit {
+    /// e.g. "memory limit (512 MB)"
+    pub fn describe(self, limits: &ResourceLimits) -> String {
+        let value = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+        match self {
+            Limit::Memory => format!("memory limit ({} MB)", value(limits.memory_mb)),
+            Limit::Cpu => format!("CPU time limit ({}s)", value(limits.cpu_seconds)),
+            Limit::OpenFiles => format!("open files limit ({})", value(limits.open_files)),
+            Limit::Processes => format!("process limit ({})", value(limits.processes)),
+        }
+    }
+
+    fn is_set(self, limits: &ResourceLimits) -> bool {
+        match self {
+            Limit::Memory => limits.memory_mb.is_some(),
+            Limit::Cpu => limits.cpu_seconds.is_some(),
+            Limit::OpenFiles => limits.open_files.is_some(),
+            Limit::Processes => limits.processes.is_some(),
+        }
+    }
+}
+
+/// Resident memory and CPU time of a server and its child processes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub rss_bytes: u64,
+    pub cpu_ms: u64,
+}
+
+/// Configures `cmd` to start with the given limits.
+#[cfg(unix)]
+pub fn apply(cmd: &mut Command, limits: &ResourceLimits) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+
+    let mut rlimits: Vec<(_, libc::rlim_t, libc::rlim_t)> = Vec::new();
+    if let Some(mb) = limits.memory_mb {
+        let bytes = mb.saturating_mul(1024 * 1024) as libc::rlim_t;
+        rlimits.push((libc::RLIMIT_AS, bytes, bytes));
+    }
+    if let Some(secs) = limits.cpu_seconds {
+        let secs = secs.max(1);
+        rlimits.push((
+            libc::RLIMIT_CPU,
+            secs as libc::rlim_t,
+            (secs + CPU_GRACE_SECS) as libc::rlim_t,
+        ));
+    }
+    if let Some(files) = limits.open_files {
+        rlimits.push((
+            libc::RLIMIT_NOFILE,
+            files as libc::rlim_t,
+            files as libc::rlim_t,
+        ));
+    }
+    if let Some(processes) = limits.processes {
+        let processes = processes as libc::rlim_t;
+        rlimits.push((libc::RLIMIT_NPROC, processes, processes));
+    }
+
+    // Only async-signal-safe calls here: this runs in the forked child
+    unsafe {
+        cmd.pre_exec(move || {
+            for (resource, soft, hard) in &rlimits {
+                let limit = libc::rlimit {
+                    rlim_cur: *soft,
+                    rlim_max: *hard,
+                };
+                if libc::setrlimit(*resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn apply(_cmd: &mut Command, _limits: &ResourceLimits) -> Result<(), String> {
+    Err("resource limits are only supported on Linux and macOS".to_string())
+}
+
+/// Limit hinted at by a line the server wrote to stderr.
+pub fn stderr_hint(line: &str, limits: &ResourceLimits) -> Option<Limit> {
+    const PATTERNS: &[(&str, Limit)] = &[
+        ("out of memory", Limit::Memory),
+        ("MemoryError", Limit::Memory),
+        ("Cannot allocate memory", Limit::Memory),
+        ("memory allocation of", Limit::Memory),
+        ("std::bad_alloc", Limit::Memory),
+        ("Too many open files", Limit::OpenFiles),
+        ("EMFILE", Limit::OpenFiles),
+        ("Resource temporarily unavailable", Limit::Processes),
+        ("EAGAIN", Limit::Processes),
+    ];
+    PATTERNS
+        .iter()
+        .find(|(pattern, _)| line.contains(pattern))
+        .map(|(_, limit)| *limit)
+        .filter(|limit| limit.is_set(limits))
+}
+
+/// The limit a server exceeded, judging by how it exited.
+pub fn exceeded(
+    status: &ExitStatus,
+    limits: &ResourceLimits,
+    hint: Option<Limit>,
+) -> Option<Limit> {
+    if status.success() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if status.signal() == Some(libc::SIGXCPU) && limits.cpu_seconds.is_some() {
+            return Some(Limit::Cpu);
+        }
+    }
+    hint.filter(|limit| limit.is_set(limits))
+}
+
+/// Memory and CPU usage of each of `pids` and its descendants.
+///
+/// Reads `/proc` once for all of them; pids that are not running are left out.
+#[cfg(target_os = "linux")]
+pub fn usage(pids: &[u32]) -> HashMap<u32, Usage> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return HashMap::new();
+    };
+    let stats: Vec<(u32, ProcStat)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            Some((pid, parse_stat(&stat)?))
+        })
+        .collect();
+    pids.iter()
+        .filter_map(|&pid| Some((pid, tree_usage(&stats, pid)?)))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn tree_usage(stats: &[(u32, ProcStat)], pid: u32) -> Option<Usage> {
+    if !stats.iter().any(|(p, _)| *p == pid) {
+        return None;
+    }
+
+    // Repeat until no new descendant is found; pids can wrap around, so a
+    // child may be listed before its parent
+    let mut tree = std::collections::HashSet::from([pid]);
+    loop {
+        let before = tree.len();
+        for (p, stat) in stats {
+            if tree.contains(&stat.ppid) {
+                tree.insert(*p);
+            }
+        }
+        if tree.len() == before {
+            break;
+        }
+    }
+
+    let (ticks_per_sec, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_CLK_TCK).max(1) as u64,
+            libc::sysconf(libc::_SC_PAGESIZE).max(1) as u64,
+        )
+    };
+    let mut usage = Usage::default();
+    for (_, stat) in stats.iter().filter(|(p, _)| tree.contains(p)) {
+        usage.rss_bytes += stat.rss_pages * page_size;
+        usage.cpu_ms += stat.cpu_ticks * 1000 / ticks_per_sec;
+    }
+    Some(usage)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn usage(_pids: &[u32]) -> HashMap<u32, Usage> {
+    HashMap::new()
+}
+
+/// Fields of `/proc/<pid>/stat`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, PartialEq)]
+struct ProcStat {
+    ppid: u32,
+    /// utime + stime
+    cpu_ticks: u64,
+    rss_pages: u64,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(stat: &str) -> Option<ProcStat> {
+    // The command name in parentheses may contain spaces
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    Some(ProcStat {
+        ppid: field(4)? as u32,
+        cpu_ticks: field(14)? + field(15)?,
+        rss_pages: field(24)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "4242 (node (worker)) S 4200 4242 4242 0 -1 4194560 9134 0 0 0 \
+                    150 25 0 0 20 0 11 0 81234 1137410048 12800 18446744073709551615";
+        assert_eq!(
+            parse_stat(stat),
+            Some(ProcStat {
+                ppid: 4200,
+                cpu_ticks: 175,
+                rss_pages: 12800,
+            })
+        );
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_usage() {
+        let own = std::process::id();
+        let usage = usage(&[own, u32::MAX]);
+        assert!(usage[&own].rss_bytes > 0);
+        assert!(!usage.contains_key(&u32::MAX));
+    }
+
+    #[test]
+    fn test_stderr_hint() {
+        let limits = ResourceLimits {
+            memory_mb: Some(512),
+            ..Default::default()
+        };
+        assert_eq!(
+            stderr_hint(
+                "FATAL ERROR: Reached heap limit Allocation failed - JavaScript heap out of memory",
+                &limits
+            ),
+            Some(Limit::Memory)
+        );
+        // Only limits that are configured are reported
+        assert_eq!(
+            stderr_hint("Error: EMFILE: too many open files", &limits),
+            None
+        );
+        assert_eq!(stderr_hint("listening on stdio", &limits), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exceeded() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let limits = ResourceLimits {
+            memory_mb: Some(512),
+            cpu_seconds: Some(60),
+            ..Default::default()
+        };
+        let killed = |signal| ExitStatus::from_raw(signal);
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+
+        assert_eq!(
+            exceeded(&killed(libc::SIGXCPU), &limits, None),
+            Some(Limit::Cpu)
+        );
+        assert_eq!(
+            exceeded(&killed(libc::SIGABRT), &limits, Some(Limit::Memory)),
+            Some(Limit::Memory)
+        );
+        assert_eq!(exceeded(&exited(1), &limits, None), None);
+        // A clean exit is not a limit, whatever was logged
+        assert_eq!(exceeded(&exited(0), &limits, Some(Limit::Memory)), None);
+        assert_eq!(
+            exceeded(&killed(libc::SIGXCPU), &ResourceLimits::default(), None),
+            None
+        );
+    }
+}
@@ -1,204 +1,1527 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::{debug, info, trace, warn};
 
+use super::audit;
+use super::catalog::{self, Catalog};
 use super::client::McpClient;
-use super::config::{McpConfig, McpServerConfig};
+use super::client::McpTool;
+use super::config::{
+    EnvInheritance, HealthCheck, McpConfig, McpServerConfig, Startup, ToolPermission,
+};
+use super::content;
+use super::limits;
+use super::monitor::{Monitor, MonitorHandler};
+use super::naming;
+use super::project::{self, ProjectServer, Trust, TrustStore};
+use super::queue::QueueStats;
+use super::schema;
+use super::stats::{Outcome, Stats, StatsSnapshot};
+use crate::error::{AppResult, ConfigError, McpError};
 use crate::extensions;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerStatus {
+    Running,
+    Disabled,
+    /// Project server waiting for (or refused) the user's trust
+    Untrusted,
+    /// Not running (lazy startup or stopped after being idle); starts on first use
+    Idle,
+    /// Starting in the background; listed with its cached catalog until ready
+    Starting,
+    /// Running, but the last `failureThreshold` health check pings failed
+    Unhealthy,
+    /// Terminated for exceeding one of its `limits`; starts again on next use
+    OverLimit,
+}
+
+/// Asks the user a yes/no question (title, message). Called from a blocking thread.
+/// Used for project server trust and for tools whose permission is `ask`.
+pub type ConfirmHandler = Arc<dyn Fn(String, String) -> bool + Send + Sync>;
+
+/// Notified when the server list changes outside of a `load_servers` call
+/// (a background start or health check restart finished or failed).
+pub type ChangeHandler = Arc<dyn Fn() + Send + Sync>;
+
+type ChangeHandlerSlot = Arc<std::sync::RwLock<Option<ChangeHandler>>>;
+
+/// One lock per server, so on-demand, background and health check restarts
+/// start a server once while different servers start in parallel
+#[derive(Default)]
+struct StartLocks(std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>);
+
+impl StartLocks {
+    fn get(&self, server: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(server.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// A known server that is not running
+#[derive(Debug, Clone)]
+struct InactiveServer {
+    display_name: String,
+    status: ServerStatus,
+    /// Config to start an idle, starting or over-limit server with
+    config: Option<McpServerConfig>,
+    /// Why the server stopped (the limit it exceeded)
+    reason: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerInfo {
     pub name: String,
     pub display_name: String,
+    pub status: ServerStatus,
     pub tools: Vec<super::client::McpTool>,
     pub resources: Vec<super::client::McpResource>,
+    /// Request queue of a running server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueStats>,
+    /// Tools and resources come from the on-disk catalog, not the running server
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+    /// Round-trip time of the last successful health check ping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_ping_ms: Option<u64>,
+    /// Resident memory of a running server and its child processes (Linux)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    /// CPU time used by a running server and its child processes (Linux)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_ms: Option<u64>,
+    /// Why a server with status `overlimit` was terminated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_reason: Option<String>,
 }
 
 pub struct McpManager {
     clients: Arc<RwLock<HashMap<String, Arc<McpClient>>>>,
-    loading: AtomicBool,
+    /// Configured servers that are not running (disabled, untrusted, idle, starting)
+    inactive: Arc<RwLock<HashMap<String, InactiveServer>>>,
+    /// Serializes loads; a load requested while one runs (e.g. a profile
+    /// switch during startup) waits for it and then applies the current config
+    loading: tokio::sync::Mutex<()>,
+    /// Start every server on first use instead of at load (headless CLI)
+    on_demand: AtomicBool,
+    /// Serializes on-demand and background starts of each server
+    starting: Arc<StartLocks>,
+    confirm_handler: std::sync::RwLock<Option<ConfirmHandler>>,
+    change_handler: ChangeHandlerSlot,
+    stats: Arc<Stats>,
+    monitor: Monitor,
 }
 
 impl McpManager {
     pub fn new() -> Self {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
-            loading: AtomicBool::new(false),
+            inactive: Arc::new(RwLock::new(HashMap::new())),
+            loading: tokio::sync::Mutex::new(()),
+            on_demand: AtomicBool::new(false),
+            starting: Arc::new(StartLocks::default()),
+            confirm_handler: std::sync::RwLock::new(None),
+            change_handler: Arc::new(std::sync::RwLock::new(None)),
+            stats: Arc::new(Stats::default()),
+            monitor: Monitor::default(),
         }
     }
 
-    pub async fn load_servers(&self) -> Result<Vec<String>, String> {
-        // Prevent concurrent loading - if already loading, wait and return existing servers
-        if self
-            .loading
-            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-            .is_err()
-        {
-            eprintln!("[MCP Manager] load_servers() - already loading, skipping");
-            // Return existing loaded servers
-            return Ok(self.clients.read().await.keys().cloned().collect());
-        }
+    /// Sets the handler used to ask the user before starting untrusted project
+    /// servers and before running tools that require approval.
+    pub fn set_confirm_handler(&self, handler: ConfirmHandler) {
+        *self.confirm_handler.write().unwrap() = Some(handler);
+    }
+
+    /// Sets the handler notified when servers started in the background become
+    /// ready, so the UI can replace their cached catalogs.
+    pub fn set_change_handler(&self, handler: ChangeHandler) {
+        *self.change_handler.write().unwrap() = Some(handler);
+    }
+
+    /// Sets the handler that receives the JSON-RPC traffic and stderr output
+    /// of every server, for the inspector window.
+    pub fn set_monitor_handler(&self, handler: MonitorHandler) {
+        self.monitor.set_handler(handler);
+    }
+
+    /// Registers every server as idle on load, so only the servers that are
+    /// used get started. For one-off commands that need a single server.
+    pub fn set_start_on_demand(&self, on_demand: bool) {
+        self.on_demand.store(on_demand, Ordering::SeqCst);
+    }
+
+    /// Returns `None` if no handler is set (e.g. headless use).
+    async fn confirm(&self, title: String, message: String) -> Option<bool> {
+        let handler = self.confirm_handler.read().unwrap().clone()?;
+        tokio::task::spawn_blocking(move || handler(title, message))
+            .await
+            .ok()
+    }
+
+    /// Spawns and initializes a server, registering it on success.
+    async fn start_server(
+        &self,
+        name: &str,
+        display_name: &str,
+        config: &McpServerConfig,
+    ) -> AppResult<()> {
+        let client = start_client(name, display_name, config, &self.stats, &self.monitor).await?;
+        self.clients
+            .write()
+            .await
+            .insert(name.to_string(), Arc::new(client));
+        Ok(())
+    }
+
+    /// Registers a server as starting and starts it in a background task.
+    ///
+    /// Used when a catalog from a previous run is cached: the server is listed
+    /// with it right away and the change handler fires once it is running.
+    async fn start_in_background(&self, name: &str, display_name: &str, config: &McpServerConfig) {
+        self.inactive.write().await.insert(
+            name.to_string(),
+            InactiveServer {
+                display_name: display_name.to_string(),
+                status: ServerStatus::Starting,
+                config: Some(config.clone()),
+                reason: None,
+            },
+        );
+
+        let clients = self.clients.clone();
+        let inactive = self.inactive.clone();
+        let starting = self.starting.clone();
+        let change_handler = self.change_handler.clone();
+        let stats = self.stats.clone();
+        let monitor = self.monitor.clone();
+        let (name, display_name, config) =
+            (name.to_string(), display_name.to_string(), config.clone());
+
+        tauri::async_runtime::spawn(async move {
+            let lock = starting.get(&name);
+            let _guard = lock.lock().await;
+            // Started on first use or reconfigured while waiting for the lock
+            if !still_starting(&inactive, &name, &config).await {
+                return;
+            }
+
+            let result = start_client(&name, &display_name, &config, &stats, &monitor).await;
+
+            let mut clients = clients.write().await;
+            let mut inactive = inactive.write().await;
+            if !inactive.get(&name).is_some_and(|s| s.is_starting(&config)) {
+                // Removed or changed by a reload while starting
+                return;
+            }
+            inactive.remove(&name);
+            match result {
+                Ok(client) => {
+                    info!("{} ready - catalog refreshed", name);
+                    clients.insert(name, Arc::new(client));
+                }
+                Err(e) => warn!("{}", e),
+            }
+            drop((clients, inactive));
+            notify_change(&change_handler);
+        });
+    }
+
+    pub async fn load_servers(&self) -> AppResult<Vec<String>> {
+        let _guard = match self.loading.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                debug!("load_servers() - waiting for the running load");
+                self.loading.lock().await
+            }
+        };
 
-        eprintln!("[MCP Manager] load_servers() started");
+        debug!("load_servers() started");
         let result = self.do_load_servers().await;
-        self.loading.store(false, Ordering::SeqCst);
-        eprintln!("[MCP Manager] load_servers() completed");
+        debug!("load_servers() completed");
         result
     }
 
-    async fn do_load_servers(&self) -> Result<Vec<String>, String> {
+    async fn do_load_servers(&self) -> AppResult<Vec<String>> {
         let config = McpConfig::load()?;
         let mut loaded = Vec::new();
+        // Every server that should be running after this load
+        let mut desired = HashSet::new();
 
-        eprintln!(
-            "[MCP Manager] Found {} servers in config file",
-            config.mcp_servers.len()
+        info!(
+            "Found {} servers in config file (profile: {})",
+            config.mcp_servers.len(),
+            config.active_profile.as_deref().unwrap_or("none")
         );
 
+        // Idle entries are kept so servers stopped for inactivity stay stopped,
+        // starting entries so their background start is not lost
+        self.inactive.write().await.retain(|_, s| s.has_config());
+
         // Load servers from claude_desktop_config.json
-        for (name, server_config) in config.mcp_servers {
-            // Skip if already loaded
-            if self.clients.read().await.contains_key(&name) {
-                eprintln!("[MCP] Skipping {} - already loaded", name);
+        for (name, server_config) in &config.mcp_servers {
+            if !config.profile_includes_server(name) {
                 continue;
             }
 
+            if server_config.disabled {
+                info!("Skipping {} - disabled in config", name);
+                self.set_inactive(name, name, ServerStatus::Disabled).await;
+                continue;
+            }
+
+            let mut server_config = server_config.clone();
+            config.apply_profile_env(&mut server_config);
+
+            desired.insert(name.clone());
             // 使用 name 作為 display_name（手動設定的 server 沒有獨立的 display_name）
-            match McpClient::spawn(&name, &name, &server_config) {
-                Ok(mut client) => {
-                    if let Err(e) = client.initialize().await {
-                        eprintln!("Failed to initialize MCP server '{}': {}", name, e);
-                        continue;
-                    }
-                    loaded.push(name.clone());
-                    self.clients.write().await.insert(name, Arc::new(client));
-                }
-                Err(e) => {
-                    eprintln!("Failed to spawn MCP server '{}': {}", name, e);
-                }
+            match self.activate_server(name, name, &server_config).await {
+                Ok(true) => loaded.push(name.clone()),
+                Ok(false) => debug!("Skipping {} - already loaded", name),
+                Err(e) => warn!("{}", e),
             }
         }
 
+        // Load project servers from registered workspaces (.mcp.json)
+        loaded.extend(self.load_project_servers(&config, &mut desired).await);
+
         // Load servers from installed extensions
-        eprintln!("[MCP Manager] Loading extension servers...");
+        debug!("Loading extension servers...");
 
-        match extensions::extension_get_mcp_servers().await {
+        let extensions_loaded = match extensions::extension_get_mcp_servers().await {
             Ok(ext_servers) => {
-                eprintln!("[MCP] Found {} extension MCP servers", ext_servers.len());
+                info!("Found {} extension MCP servers", ext_servers.len());
                 for ext_server in ext_servers {
-                    // Use extension_id as server name to avoid conflicts
-                    let server_name = format!("ext_{}", ext_server.extension_id);
-
-                    // Skip if already loaded
-                    if self.clients.read().await.contains_key(&server_name) {
-                        eprintln!("[MCP] Skipping {} - already loaded", server_name);
+                    if !config.profile_includes_extension(&ext_server.extension_id) {
                         continue;
                     }
 
-                    let server_config = McpServerConfig {
+                    // Use extension_id as server name to avoid conflicts
+                    let server_name = format!("ext_{}", ext_server.extension_id);
+
+                    let mut server_config = McpServerConfig {
                         command: ext_server.command.clone(),
                         args: ext_server.args.clone(),
                         env: ext_server.env.clone(),
+                        sandbox: ext_server.sandbox.clone(),
+                        ..Default::default()
                     };
+                    config.apply_profile_env(&mut server_config);
 
-                    eprintln!(
-                        "[MCP] Loading extension server '{}' ({}) - cmd: {} {:?}",
-                        server_name, ext_server.name, server_config.command, server_config.args
+                    // Args are not logged: they can hold user_config values such as API keys
+                    info!(
+                        "Loading extension server '{}' ({}) - cmd: {}",
+                        server_name, ext_server.name, server_config.command
                     );
 
+                    desired.insert(server_name.clone());
                     // ext_server.name 是 display_name（如 "Filesystem"）
-                    match McpClient::spawn(&server_name, &ext_server.name, &server_config) {
-                        Ok(mut client) => {
-                            if let Err(e) = client.initialize().await {
-                                eprintln!("[MCP] Failed to initialize '{}': {}", server_name, e);
-                                continue;
-                            }
-                            eprintln!("[MCP] Successfully loaded '{}'", server_name);
-                            loaded.push(server_name.clone());
-                            self.clients
-                                .write()
-                                .await
-                                .insert(server_name, Arc::new(client));
-                        }
-                        Err(e) => {
-                            eprintln!("[MCP] Failed to spawn '{}': {}", server_name, e);
+                    match self
+                        .activate_server(&server_name, &ext_server.name, &server_config)
+                        .await
+                    {
+                        Ok(true) => {
+                            info!("Successfully loaded '{}'", server_name);
+                            loaded.push(server_name);
                         }
+                        Ok(false) => debug!("Skipping {} - already loaded", server_name),
+                        Err(e) => warn!("{}", e),
                     }
                 }
+                true
             }
             Err(e) => {
-                eprintln!("[MCP] Failed to get extension MCP servers: {}", e);
+                warn!("Failed to get extension MCP servers: {}", e.message);
+                false
             }
-        }
+        };
+
+        // Stop servers that are no longer configured, disabled, or outside the active profile.
+        // Extension servers are kept if the extension list could not be read.
+        self.clients.write().await.retain(|name, _| {
+            let keep = desired.contains(name) || (!extensions_loaded && name.starts_with("ext_"));
+            if !keep {
+                info!("Stopped {} - no longer active", name);
+            }
+            keep
+        });
+        self.inactive
+            .write()
+            .await
+            .retain(|name, s| !s.has_config() || desired.contains(name));
 
         Ok(loaded)
     }
 
+    /// Starts an eager server, or registers a lazy one as idle.
+    ///
+    /// A server that was stopped for inactivity stays idle as long as its
+    /// config is unchanged. An eager server with a cached catalog starts in
+    /// the background. Returns whether the server was (re)started.
+    async fn activate_server(
+        &self,
+        name: &str,
+        display_name: &str,
+        config: &McpServerConfig,
+    ) -> Result<bool, String> {
+        let previous = self
+            .inactive
+            .read()
+            .await
+            .get(name)
+            .filter(|s| s.config.as_ref() == Some(config))
+            .map(|s| s.status);
+
+        if previous == Some(ServerStatus::Starting) {
+            return Ok(false);
+        }
+
+        if config.startup == Startup::Lazy
+            || previous == Some(ServerStatus::Idle)
+            || self.on_demand.load(Ordering::SeqCst)
+        {
+            let running = self
+                .clients
+                .read()
+                .await
+                .get(name)
+                .map(|c| c.config == *config);
+            match running {
+                // Started on demand earlier; keep it
+                Some(true) => return Ok(false),
+                Some(false) => {
+                    self.clients.write().await.remove(name);
+                }
+                None => {}
+            }
+            self.set_idle(name, display_name, config).await;
+            return Ok(false);
+        }
+
+        self.inactive.write().await.remove(name);
+
+        let running = self
+            .clients
+            .read()
+            .await
+            .get(name)
+            .map(|c| c.config == *config);
+        if running != Some(true) && Catalog::load().get(name, config).is_some() {
+            if running.is_some() {
+                self.clients.write().await.remove(name);
+                info!("Restarting {} - config changed", name);
+            }
+            self.start_in_background(name, display_name, config).await;
+            return Ok(true);
+        }
+
+        self.ensure_server(name, display_name, config).await
+    }
+
+    /// Returns a running client, starting an idle server on first use.
+    async fn client_for(&self, server: &str) -> AppResult<Arc<McpClient>> {
+        if let Some(client) = self.clients.read().await.get(server) {
+            return Ok(client.clone());
+        }
+
+        let lock = self.starting.get(server);
+        let _guard = lock.lock().await;
+        // Another call may have started it while we waited
+        if let Some(client) = self.clients.read().await.get(server) {
+            return Ok(client.clone());
+        }
+
+        // Servers still starting in the background are started here instead
+        let idle = self.inactive.read().await.get(server).and_then(|s| {
+            let config = s.config.clone()?;
+            Some((s.display_name.clone(), config))
+        });
+        let Some((display_name, config)) = idle else {
+            return Err(McpError::ServerNotFound(server.to_string()).into());
+        };
+
+        info!("Starting {} on first use", server);
+        self.start_server(server, &display_name, &config).await?;
+        self.inactive.write().await.remove(server);
+
+        self.clients
+            .read()
+            .await
+            .get(server)
+            .cloned()
+            .ok_or_else(|| McpError::ServerNotFound(server.to_string()).into())
+    }
+
+    /// Periodically stops servers that were not used for their `idleTimeoutMinutes`.
+    /// They are listed as idle and start again on the next call.
+    pub fn start_idle_monitor(&self) {
+        let clients = self.clients.clone();
+        let inactive = self.inactive.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                stop_idle_servers(&clients, &inactive).await;
+            }
+        });
+    }
+
+    /// Pings servers that have a `healthCheck` configured. Servers whose pings
+    /// keep failing are listed as unhealthy and, with `restart`, restarted.
+    ///
+    /// Also removes servers that were terminated for exceeding their `limits`.
+    pub fn start_health_monitor(&self) {
+        let clients = self.clients.clone();
+        let inactive = self.inactive.clone();
+        let starting = self.starting.clone();
+        let change_handler = self.change_handler.clone();
+        let stats = self.stats.clone();
+        let monitor = self.monitor.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_CHECK_TICK);
+            loop {
+                interval.tick().await;
+                if remove_over_limit_servers(&clients, &inactive).await {
+                    notify_change(&change_handler);
+                }
+
+                let due: Vec<(String, Arc<McpClient>, HealthCheck)> = clients
+                    .read()
+                    .await
+                    .iter()
+                    .filter_map(|(name, client)| {
+                        let check = client.config.health_check.clone()?;
+                        let every = std::time::Duration::from_secs(check.interval_seconds);
+                        (check.interval_seconds > 0 && client.begin_health_check(every))
+                            .then(|| (name.clone(), client.clone(), check))
+                    })
+                    .collect();
+
+                // Each ping runs on its own so a hung server does not delay the others
+                for (name, client, check) in due {
+                    let clients = clients.clone();
+                    let inactive = inactive.clone();
+                    let starting = starting.clone();
+                    let change_handler = change_handler.clone();
+                    let stats = stats.clone();
+                    let monitor = monitor.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let restart = ping_server(&name, &client, &check).await;
+                        if restart {
+                            restart_unhealthy(
+                                &name, client, &clients, &inactive, &starting, &stats, &monitor,
+                            )
+                            .await;
+                            notify_change(&change_handler);
+                        }
+                    });
+                }
+            }
+        });
+    }
+
+    /// Starts a server unless it is already running with the same config.
+    /// A running server whose config changed is restarted.
+    ///
+    /// Returns whether the server was (re)started.
+    async fn ensure_server(
+        &self,
+        name: &str,
+        display_name: &str,
+        config: &McpServerConfig,
+    ) -> Result<bool, String> {
+        if let Some(client) = self.clients.read().await.get(name) {
+            if client.config == *config {
+                return Ok(false);
+            }
+        }
+        if self.clients.write().await.remove(name).is_some() {
+            info!("Restarting {} - config changed", name);
+        }
+        self.start_server(name, display_name, config).await?;
+        Ok(true)
+    }
+
+    /// Starts trusted project servers, asking the user about newly seen ones.
+    async fn load_project_servers(
+        &self,
+        config: &McpConfig,
+        desired: &mut HashSet<String>,
+    ) -> Vec<String> {
+        let project_servers: Vec<ProjectServer> = project::collect_project_servers(config)
+            .into_iter()
+            .filter(|s| config.profile_includes_server(&s.id))
+            .collect();
+        let mut loaded = Vec::new();
+
+        info!(
+            "Found {} project servers in {} workspaces",
+            project_servers.len(),
+            config.workspaces.len()
+        );
+
+        let mut trust_store = TrustStore::load();
+        let mut trust_changed = false;
+
+        for server in project_servers {
+            if server.config.disabled {
+                self.set_inactive(&server.id, &server.display_name, ServerStatus::Disabled)
+                    .await;
+                continue;
+            }
+
+            let trusted = match trust_store.check(&server) {
+                Trust::Trusted => true,
+                Trust::Denied => false,
+                Trust::Unknown => {
+                    match self.confirm(trust_title(&server), trust_message(&server)).await {
+                        Some(decision) => {
+                            trust_store.set(&server, decision);
+                            trust_changed = true;
+                            decision
+                        }
+                        None => false,
+                    }
+                }
+            };
+
+            if !trusted {
+                info!("Not starting {} - not trusted", server.id);
+                self.set_inactive(&server.id, &server.display_name, ServerStatus::Untrusted)
+                    .await;
+                continue;
+            }
+
+            let mut server_config = server.config.clone();
+            config.apply_profile_env(&mut server_config);
+
+            desired.insert(server.id.clone());
+            match self
+                .activate_server(&server.id, &server.display_name, &server_config)
+                .await
+            {
+                Ok(true) => loaded.push(server.id),
+                Ok(false) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        if trust_changed {
+            if let Err(e) = trust_store.save() {
+                warn!("{}", e);
+            }
+        }
+
+        loaded
+    }
+
+    /// Switches the active profile (`None` runs every server) and applies the
+    /// difference: servers outside the profile are stopped, missing ones are
+    /// started and servers whose effective env changed are restarted.
+    ///
+    /// The choice is saved so it is restored on the next launch.
+    pub async fn switch_profile(&self, profile: Option<String>) -> AppResult<Vec<String>> {
+        let mut config = McpConfig::load()?;
+        if let Some(name) = &profile {
+            if !config.profiles.contains_key(name) {
+                return Err(ConfigError::ProfileNotFound(name.clone()).into());
+            }
+        }
+        config.active_profile = profile;
+        config.save()?;
+
+        info!(
+            "Switching to profile: {}",
+            config.active_profile.as_deref().unwrap_or("none")
+        );
+        self.load_servers().await
+    }
+
+    /// Records the user's trust decision for a project server.
+    ///
+    /// Revoking trust stops the server; granting it takes effect on the next load.
+    pub async fn set_project_trust(
+        &self,
+        workspace: &str,
+        name: &str,
+        trusted: bool,
+    ) -> AppResult<()> {
+        let config = McpConfig::load()?;
+        let workspace = std::path::Path::new(workspace);
+        let server = project::collect_project_servers(&config)
+            .into_iter()
+            .find(|s| s.name == name && s.workspace == workspace)
+            .ok_or_else(|| McpError::ServerNotFound(name.to_string()))?;
+
+        let mut trust_store = TrustStore::load();
+        trust_store.set(&server, trusted);
+        trust_store.save()?;
+
+        if !trusted {
+            self.clients.write().await.remove(&server.id);
+            self.set_inactive(&server.id, &server.display_name, ServerStatus::Untrusted)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn set_idle(&self, name: &str, display_name: &str, config: &McpServerConfig) {
+        self.inactive.write().await.insert(
+            name.to_string(),
+            InactiveServer {
+                display_name: display_name.to_string(),
+                status: ServerStatus::Idle,
+                config: Some(config.clone()),
+                reason: None,
+            },
+        );
+    }
+
+    async fn set_inactive(&self, name: &str, display_name: &str, status: ServerStatus) {
+        self.inactive.write().await.insert(
+            name.to_string(),
+            InactiveServer {
+                display_name: display_name.to_string(),
+                status,
+                config: None,
+                reason: None,
+            },
+        );
+    }
+
     pub async fn list_servers(&self) -> Vec<ServerInfo> {
-        let clients = self.clients.read().await;
-        let servers: Vec<ServerInfo> = clients
+        let settings = McpConfig::load()
+            .map(|c| c.server_settings)
+            .unwrap_or_default();
+        let clients: Vec<(String, Arc<McpClient>)> = self
+            .clients
+            .read()
+            .await
+            .iter()
+            .map(|(name, client)| (name.clone(), client.clone()))
+            .collect();
+        // One pass over /proc for all servers, off the async runtime
+        let pids: Vec<u32> = clients.iter().map(|(_, client)| client.pid()).collect();
+        let usage = tokio::task::spawn_blocking(move || limits::usage(&pids))
+            .await
+            .unwrap_or_default();
+
+        let mut servers: Vec<ServerInfo> = clients
             .iter()
             .map(|(name, client)| {
-                eprintln!("[MCP] list_servers: name={}, display_name={}", name, client.display_name);
+                trace!(
+                    "list_servers: name={}, display_name={}",
+                    name,
+                    client.display_name
+                );
+                let unhealthy = client
+                    .config
+                    .health_check
+                    .as_ref()
+                    .is_some_and(|check| client.is_unhealthy(check.failure_threshold));
+                let usage = usage.get(&client.pid());
                 ServerInfo {
                     name: name.clone(),
                     display_name: client.display_name.clone(),
-                    tools: client.tools.clone(),
+                    status: if unhealthy {
+                        ServerStatus::Unhealthy
+                    } else {
+                        ServerStatus::Running
+                    },
+                    tools: naming::apply(name, settings.get(name), &client.tools),
                     resources: client.resources.clone(),
+                    queue: Some(client.queue_stats()),
+                    stale: false,
+                    last_ping_ms: client.last_ping().map(|d| d.as_millis() as u64),
+                    rss_bytes: usage.map(|u| u.rss_bytes),
+                    cpu_ms: usage.map(|u| u.cpu_ms),
+                    exit_reason: None,
                 }
             })
             .collect();
+
+        // Idle and starting servers show the catalog from their last run
+        let catalog = Catalog::load();
+        let inactive = self.inactive.read().await;
+        // Skip servers that were stopped since the running ones were listed
+        let listed: HashSet<String> = clients.into_iter().map(|(name, _)| name).collect();
+        let inactive = inactive.iter().filter(|(name, _)| !listed.contains(*name));
+        servers.extend(inactive.map(|(name, server)| {
+            let cached = server
+                .config
+                .as_ref()
+                .and_then(|config| catalog.get(name, config));
+            ServerInfo {
+                name: name.clone(),
+                display_name: server.display_name.clone(),
+                status: server.status,
+                tools: cached
+                    .map(|c| naming::apply(name, settings.get(name), &c.tools))
+                    .unwrap_or_default(),
+                resources: cached.map(|c| c.resources.clone()).unwrap_or_default(),
+                queue: None,
+                stale: cached.is_some(),
+                last_ping_ms: None,
+                rss_bytes: None,
+                cpu_ms: None,
+                exit_reason: server.reason.clone(),
+            }
+        }));
+
+        warn_tool_collisions(&servers);
         servers
     }
 
-    pub async fn call_tool(
+    /// Tools of a server under their exposed names, starting it if idle.
+    pub async fn list_tools(&self, server: &str) -> AppResult<Vec<McpTool>> {
+        let client = self.client_for(server).await?;
+        let settings = McpConfig::load()
+            .map(|c| c.server_settings)
+            .unwrap_or_default();
+        Ok(naming::apply(server, settings.get(server), &client.tools))
+    }
+
+    /// Calls a tool and records the call in the audit log.
+    pub async fn call_tool(&self, server: &str, tool: &str, arguments: Value) -> AppResult<Value> {
+        // The permission policy lives in the config; without it, fail closed
+        let config = McpConfig::load().map_err(|e| {
+            McpError::PermissionDenied(format!(
+                "Tool '{}' on server '{}' was not run because the permission policy cannot be read: {}",
+                tool, server, e
+            ))
+        })?;
+        let audit_config = &config.audit;
+        let audited_arguments = audit_config.enabled.then(|| arguments.clone());
+        let started = std::time::Instant::now();
+
+        let result = self.dispatch_tool(&config, server, tool, arguments).await;
+
+        if let Some(arguments) = audited_arguments {
+            let entry = audit::entry(
+                audit_config,
+                server,
+                tool,
+                &arguments,
+                started.elapsed(),
+                &result,
+            );
+            audit::record(audit_config, &entry);
+        }
+
+        // Keep oversized output out of the IPC reply
+        result.map(|r| content::limit_tool_result(r, &config.result_limits))
+    }
+
+    async fn dispatch_tool(
         &self,
+        config: &McpConfig,
         server: &str,
         tool: &str,
         arguments: Value,
-    ) -> Result<Value, String> {
-        // Clone the Arc to avoid holding the lock during the async call
-        let client = {
-            let clients = self.clients.read().await;
-            clients
-                .get(server)
-                .ok_or_else(|| format!("Server '{}' not found", server))?
-                .clone()
-        }; // Lock released here
-        client.call_tool(tool, arguments).await
+    ) -> AppResult<Value> {
+        // Clone the Arc to avoid holding the lock during the async call;
+        // idle servers are started here
+        let client = self.client_for(server).await?;
+
+        // The model calls tools by their exposed (prefixed or aliased) names
+        let resolved = naming::resolve(
+            server,
+            config.server_settings.get(server),
+            &client.tools,
+            tool,
+        )
+        .map_err(McpError::ToolUnavailable)?;
+        let tool = resolved.as_str();
+
+        // A denied tool is refused before its arguments are looked at
+        let permission = config.tool_permission(server, tool);
+        if permission == ToolPermission::Deny {
+            info!(
+                "Tool call denied by policy: server={}, tool={}",
+                server, tool
+            );
+            return Err(McpError::PermissionDenied(format!(
+                "Tool '{}' on server '{}' is denied by permission policy",
+                tool, server
+            ))
+            .into());
+        }
+
+        let schemas = client.tool_schemas(tool);
+
+        // Reject malformed arguments before they reach the server
+        if let Some(schemas) = schemas {
+            let instance = if arguments.is_null() {
+                serde_json::json!({})
+            } else {
+                arguments.clone()
+            };
+            if let Err(issues) = schemas.input.validate(&instance) {
+                info!(
+                    "Invalid arguments: server={}, tool={}\n{}",
+                    server,
+                    tool,
+                    schema::format_issues(&issues)
+                );
+                return Ok(schema::invalid_arguments_result(tool, &issues));
+            }
+        }
+
+        if permission == ToolPermission::Ask {
+            self.confirm_tool_call(&client, server, tool, &arguments)
+                .await?;
+        }
+
+        let started = std::time::Instant::now();
+        let result = client.call_tool(tool, arguments).await;
+        self.stats
+            .record_call(server, Some(tool), started.elapsed(), Outcome::of(&result));
+        let result = result?;
+
+        if let Some(output_schema) = schemas.and_then(|s| s.output.as_ref()) {
+            if let Err(issues) = schema::validate_result(output_schema, &result) {
+                return Err(McpError::Communication(format!(
+                    "Server '{}' returned invalid structured output for tool '{}':\n{}",
+                    server,
+                    tool,
+                    schema::format_issues(&issues)
+                ))
+                .into());
+            }
+        }
+
+        Ok(result)
     }
 
-    pub async fn read_resource(&self, server: &str, uri: &str) -> Result<Value, String> {
-        // Clone the Arc to avoid holding the lock during the async call
-        let client = {
-            let clients = self.clients.read().await;
-            clients
-                .get(server)
-                .ok_or_else(|| format!("Server '{}' not found", server))?
-                .clone()
-        }; // Lock released here
-        client.read_resource(uri).await
+    /// Asks the user before a tool with the `ask` permission is dispatched.
+    async fn confirm_tool_call(
+        &self,
+        client: &McpClient,
+        server: &str,
+        tool: &str,
+        arguments: &Value,
+    ) -> AppResult<()> {
+        let definition = client.tools.iter().find(|t| t.name == tool);
+        let title = format!("Allow tool \"{}\"?", tool);
+        let message = tool_prompt_message(&client.display_name, tool, definition, arguments);
+        match self.confirm(title, message).await {
+            Some(true) => Ok(()),
+            Some(false) => Err(McpError::PermissionDenied(format!(
+                "Tool call '{}' on server '{}' was rejected by the user",
+                tool, server
+            ))
+            .into()),
+            None => Err(McpError::PermissionDenied(format!(
+                "Tool '{}' on server '{}' requires approval, but no prompt is available",
+                tool, server
+            ))
+            .into()),
+        }
     }
 
-    pub async fn stop_server(&self, name: &str) -> Result<(), String> {
+    pub async fn read_resource(&self, server: &str, uri: &str) -> AppResult<Value> {
+        // Clone the Arc to avoid holding the lock during the async call;
+        // idle servers are started here
+        let client = self.client_for(server).await?;
+        let started = std::time::Instant::now();
+        let result = client.read_resource(uri).await;
+        self.stats
+            .record_call(server, None, started.elapsed(), Outcome::of(&result));
+        let result = result?;
+        let limits = McpConfig::load().map(|c| c.result_limits).unwrap_or_default();
+        Ok(content::limit_resource_result(result, &limits))
+    }
+
+    /// Call counts, latencies, uptime and restarts per server.
+    pub async fn get_stats(&self) -> StatsSnapshot {
+        let uptimes = server_uptimes(&*self.clients.read().await);
+        self.stats.snapshot(&uptimes)
+    }
+
+    /// Writes a stats snapshot to disk every `stats.snapshotIntervalSeconds`.
+    /// The interval is read from the config each time, so changes apply
+    /// without a restart.
+    pub fn start_stats_snapshots(&self) {
+        let clients = self.clients.clone();
+        let stats = self.stats.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let interval = McpConfig::load()
+                    .map(|c| c.stats.snapshot_interval_seconds)
+                    .unwrap_or(0);
+                if interval == 0 {
+                    tokio::time::sleep(STATS_CONFIG_CHECK_INTERVAL).await;
+                    continue;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+                let uptimes = server_uptimes(&*clients.read().await);
+                if let Err(e) = super::stats::write_snapshot(&stats.snapshot(&uptimes)) {
+                    warn!("{}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn stop_server(&self, name: &str) -> AppResult<()> {
         let mut clients = self.clients.write().await;
         clients
             .remove(name)
-            .ok_or_else(|| format!("Server '{}' not found", name))?;
+            .ok_or_else(|| McpError::ServerNotFound(name.to_string()))?;
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub async fn stop_all(&self) {
         self.clients.write().await.clear();
     }
 }
 
+impl InactiveServer {
+    /// Idle, starting and over-limit servers keep the config they start with
+    fn has_config(&self) -> bool {
+        self.config.is_some()
+    }
+    fn is_starting(&self, config: &McpServerConfig) -> bool {
+        self.status == ServerStatus::Starting && self.config.as_ref() == Some(config)
+    }
+}
+
+/// Spawns and initializes a server and records its catalog.
+async fn start_client(
+    name: &str,
+    display_name: &str,
+    config: &McpServerConfig,
+    stats: &Stats,
+    monitor: &Monitor,
+) -> AppResult<McpClient> {
+    let mut client =
+        McpClient::spawn(name, display_name, config, monitor.clone()).map_err(|reason| {
+            McpError::SpawnFailed {
+                server: name.to_string(),
+                reason,
+            }
+        })?;
+    client
+        .initialize()
+        .await
+        .map_err(|reason| McpError::InitFailed {
+            server: name.to_string(),
+            reason,
+        })?;
+    catalog::update(
+        name,
+        config,
+        client.server_version.as_deref(),
+        &client.tools,
+        &client.resources,
+    );
+    stats.record_start(name);
+    Ok(client)
+}
+
+async fn still_starting(
+    inactive: &RwLock<HashMap<String, InactiveServer>>,
+    name: &str,
+    config: &McpServerConfig,
+) -> bool {
+    inactive
+        .read()
+        .await
+        .get(name)
+        .is_some_and(|s| s.is_starting(config))
+}
+
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// How often the config is checked while stats snapshots are off
+const STATS_CONFIG_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// How often servers are checked for a due health check ping
+const HEALTH_CHECK_TICK: std::time::Duration = std::time::Duration::from_secs(5);
+
+fn notify_change(change_handler: &ChangeHandlerSlot) {
+    let handler = change_handler.read().unwrap().clone();
+    if let Some(handler) = handler {
+        handler();
+    }
+}
+
+/// Sends one health check ping. Returns true if the server just became
+/// unhealthy and should be restarted.
+async fn ping_server(name: &str, client: &McpClient, check: &HealthCheck) -> bool {
+    let wait = std::time::Duration::from_secs(check.timeout_seconds.max(1));
+    let result = client.ping(wait).await;
+    let failures = client.record_ping(&result);
+    let threshold = check.failure_threshold.max(1);
+
+    if let Err(e) = &result {
+        warn!(
+            "Health check failed for {} ({}/{}): {}",
+            name, failures, threshold, e
+        );
+        if failures == threshold {
+            warn!("{} is unhealthy", name);
+            return check.restart;
+        }
+    }
+    false
+}
+
+/// Replaces an unhealthy server with a fresh process. If the new process
+/// fails to start, the server is listed as idle and retried on next use.
+async fn restart_unhealthy(
+    name: &str,
+    client: Arc<McpClient>,
+    clients: &RwLock<HashMap<String, Arc<McpClient>>>,
+    inactive: &RwLock<HashMap<String, InactiveServer>>,
+    starting: &StartLocks,
+    stats: &Stats,
+    monitor: &Monitor,
+) {
+    let lock = starting.get(name);
+    let _guard = lock.lock().await;
+    {
+        let mut clients = clients.write().await;
+        // Only restart the instance that failed; a reload may have replaced it
+        if !clients.get(name).is_some_and(|c| Arc::ptr_eq(c, &client)) {
+            return;
+        }
+        clients.remove(name);
+    }
+    let display_name = client.display_name.clone();
+    let config = client.config.clone();
+    // Kill the hung process before starting a new one
+    drop(client);
+
+    info!("Restarting {} - unhealthy", name);
+    match start_client(name, &display_name, &config, stats, monitor).await {
+        Ok(client) => {
+            clients
+                .write()
+                .await
+                .insert(name.to_string(), Arc::new(client));
+        }
+        Err(e) => {
+            warn!("{}", e);
+            inactive.write().await.insert(
+                name.to_string(),
+                InactiveServer {
+                    display_name,
+                    status: ServerStatus::Idle,
+                    config: Some(config),
+                    reason: None,
+                },
+            );
+        }
+    }
+}
+
+async fn stop_idle_servers(
+    clients: &RwLock<HashMap<String, Arc<McpClient>>>,
+    inactive: &RwLock<HashMap<String, InactiveServer>>,
+) {
+    let mut clients = clients.write().await;
+    let idle: Vec<String> = clients
+        .iter()
+        .filter(|(_, client)| {
+            client.config.idle_timeout_minutes.is_some_and(|minutes| {
+                minutes > 0
+                    && client.queue_stats().in_flight == 0
+                    && client.idle_time().as_secs() >= minutes * 60
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    for name in idle {
+        if let Some(client) = clients.remove(&name) {
+            info!("Stopped {} - idle", name);
+            inactive.write().await.insert(
+                name,
+                InactiveServer {
+                    display_name: client.display_name.clone(),
+                    status: ServerStatus::Idle,
+                    config: Some(client.config.clone()),
+                    reason: None,
+                },
+            );
+        }
+    }
+}
+
+/// Lists servers whose process was terminated by a resource limit as
+/// over-limit. Returns whether any were found.
+async fn remove_over_limit_servers(
+    clients: &RwLock<HashMap<String, Arc<McpClient>>>,
+    inactive: &RwLock<HashMap<String, InactiveServer>>,
+) -> bool {
+    let mut clients = clients.write().await;
+    let over_limit: Vec<(String, String)> = clients
+        .iter()
+        .filter_map(|(name, client)| Some((name.clone(), client.limit_exceeded()?)))
+        .collect();
+
+    for (name, reason) in &over_limit {
+        if let Some(client) = clients.remove(name) {
+            warn!("{} was terminated - exceeded its {}", name, reason);
+            inactive.write().await.insert(
+                name.clone(),
+                InactiveServer {
+                    display_name: client.display_name.clone(),
+                    status: ServerStatus::OverLimit,
+                    config: Some(client.config.clone()),
+                    reason: Some(format!("Exceeded its {}", reason)),
+                },
+            );
+        }
+    }
+    !over_limit.is_empty()
+}
+
+fn server_uptimes(
+    clients: &HashMap<String, Arc<McpClient>>,
+) -> HashMap<String, std::time::Duration> {
+    clients
+        .iter()
+        .map(|(name, client)| (name.clone(), client.uptime()))
+        .collect()
+}
+
 impl Default for McpManager {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Logs tool names offered by more than one server; a `prefix` or `alias`
+/// in `serverSettings` tells them apart.
+fn warn_tool_collisions(servers: &[ServerInfo]) {
+    let mut owners: HashMap<&str, Vec<&str>> = HashMap::new();
+    for server in servers {
+        for tool in &server.tools {
+            owners.entry(&tool.name).or_default().push(&server.name);
+        }
+    }
+    for (tool, names) in owners {
+        if names.len() > 1 {
+            warn!(
+                "Tool '{}' is provided by several servers ({}); set a prefix or alias in serverSettings to tell them apart",
+                tool,
+                names.join(", ")
+            );
+        }
+    }
+}
+
+fn trust_title(server: &ProjectServer) -> String {
+    format!("Allow project MCP server \"{}\"?", server.name)
+}
+
+/// Shows everything the trust decision covers (see `project::fingerprint`),
+/// so nothing that changes what runs is hidden from the user.
+fn trust_message(server: &ProjectServer) -> String {
+    let config = &server.config;
+    let mut details = format!("Command: {} {}", config.command, config.args.join(" "));
+    if let Some(cwd) = &config.cwd {
+        details.push_str(&format!("\nWorking directory: {}", cwd));
+    }
+    if !config.env.is_empty() {
+        // Values as written, before `${...}` expansion
+        let mut env: Vec<_> = config.env.iter().collect();
+        env.sort();
+        details.push_str("\nEnvironment:");
+        for (key, value) in env {
+            details.push_str(&format!("\n  {}={}", key, value));
+        }
+    }
+    details.push_str(&format!(
+        "\nInherited environment: {}",
+        match &config.inherit_env {
+            EnvInheritance::All => "all variables".to_string(),
+            EnvInheritance::Clean => "none".to_string(),
+            EnvInheritance::Allowlist(names) => format!("only {}", names.join(", ")),
+        }
+    ));
+    details.push_str(&format!(
+        "\nSandbox: {}",
+        match &config.sandbox {
+            None => "none".to_string(),
+            Some(sandbox) => format!(
+                "read [{}], write [{}], network {}",
+                sandbox.read.join(", "),
+                sandbox.write.join(", "),
+                if sandbox.network {
+                    "allowed"
+                } else {
+                    "blocked"
+                }
+            ),
+        }
+    ));
+
+    format!(
+        "The workspace {} defines the MCP server \"{}\" in .mcp.json.\n\n\
+         {}\n\n\
+         It will run with your user's permissions. Only allow servers from projects you trust.",
+        server.workspace.display(),
+        server.name,
+        details
+    )
+}
+
+fn tool_prompt_message(
+    server_display_name: &str,
+    tool: &str,
+    definition: Option<&McpTool>,
+    arguments: &Value,
+) -> String {
+    const MAX_ARGS_PREVIEW: usize = 500;
+
+    let mut message = format!("Server \"{}\" wants to run \"{}\".", server_display_name, tool);
+
+    if let Some(annotations) = definition.and_then(|t| t.annotations.as_ref()) {
+        let mut hints = Vec::new();
+        if annotations.read_only_hint == Some(true) {
+            hints.push("read-only");
+        }
+        if annotations.destructive_hint == Some(true) {
+            hints.push("may modify or delete data");
+        }
+        if annotations.open_world_hint == Some(true) {
+            hints.push("accesses external systems");
+        }
+        if !hints.is_empty() {
+            message.push_str(&format!("\nDeclared by server: {}.", hints.join(", ")));
+        }
+    }
+
+    let mut args = serde_json::to_string_pretty(arguments).unwrap_or_default();
+    if args.len() > MAX_ARGS_PREVIEW {
+        let mut cut = MAX_ARGS_PREVIEW;
+        while !args.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        args.truncate(cut);
+        args.push_str("\n...");
+    }
+    message.push_str(&format!("\n\nArguments:\n{}", args));
+    message
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    /// Sleeps for `$1` seconds, then answers every request with a result that
+    /// fits `initialize`, `tools/list` and `resources/list`
+    const FAKE_SERVER: &str = r#"sleep "$1"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  [ -n "$id" ] && printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{},"serverInfo":{"name":"fake","version":"1"},"tools":[],"resources":[]}}\n' "$id"
+done
+"#;
+
+    /// The tests share the config and catalog files in the test config dir
+    static CONFIG_DIR: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Locks the test config dir and clears what earlier tests left behind
+    async fn config_dir() -> tokio::sync::MutexGuard<'static, ()> {
+        let guard = CONFIG_DIR.lock().await;
+        let _ = std::fs::remove_file(McpConfig::config_path());
+        let _ = std::fs::remove_file(Catalog::path());
+        guard
+    }
+
+    fn fake_server(delay: &str) -> McpServerConfig {
+        static SCRIPT: OnceLock<PathBuf> = OnceLock::new();
+        let script = SCRIPT.get_or_init(|| {
+            let path = McpConfig::config_path().with_file_name("fake-server.sh");
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, FAKE_SERVER).unwrap();
+            path
+        });
+        serde_json::from_value(serde_json::json!({
+            "command": "sh",
+            "args": [script, delay]
+        }))
+        .unwrap()
+    }
+
+    async fn status(manager: &McpManager, name: &str) -> Option<ServerStatus> {
+        manager
+            .list_servers()
+            .await
+            .into_iter()
+            .find(|s| s.name == name)
+            .map(|s| s.status)
+    }
+
+    #[tokio::test]
+    async fn test_lazy_server_starts_on_use_and_stops_when_idle() {
+        let _config_dir = config_dir().await;
+        let manager = McpManager::new();
+        let mut config = fake_server("0");
+        config.startup = Startup::Lazy;
+        config.idle_timeout_minutes = Some(1);
+
+        assert_eq!(
+            manager.activate_server("lazy", "Lazy", &config).await,
+            Ok(false)
+        );
+        assert_eq!(status(&manager, "lazy").await, Some(ServerStatus::Idle));
+
+        manager.list_tools("lazy").await.unwrap();
+        assert_eq!(status(&manager, "lazy").await, Some(ServerStatus::Running));
+
+        // Still in use
+        stop_idle_servers(&manager.clients, &manager.inactive).await;
+        assert_eq!(status(&manager, "lazy").await, Some(ServerStatus::Running));
+
+        tokio::time::pause();
+        tokio::time::advance(Duration::from_secs(60)).await;
+        stop_idle_servers(&manager.clients, &manager.inactive).await;
+        assert_eq!(status(&manager, "lazy").await, Some(ServerStatus::Idle));
+
+        // A reload with the same config keeps it idle
+        assert_eq!(
+            manager.activate_server("lazy", "Lazy", &config).await,
+            Ok(false)
+        );
+        assert_eq!(status(&manager, "lazy").await, Some(ServerStatus::Idle));
+    }
+
+    #[tokio::test]
+    async fn test_reload_during_background_start() {
+        let _config_dir = config_dir().await;
+        let manager = McpManager::new();
+        let old = fake_server("1");
+        let new = fake_server("0");
+        // A catalog from a previous run starts an eager server in the background
+        catalog::update("reloaded", &old, None, &[], &[]);
+
+        assert_eq!(
+            manager.activate_server("reloaded", "Reloaded", &old).await,
+            Ok(true)
+        );
+        assert_eq!(
+            status(&manager, "reloaded").await,
+            Some(ServerStatus::Starting)
+        );
+
+        // The config changed before the background start finished
+        assert_eq!(
+            manager.activate_server("reloaded", "Reloaded", &new).await,
+            Ok(true)
+        );
+        assert_eq!(
+            status(&manager, "reloaded").await,
+            Some(ServerStatus::Running)
+        );
+
+        // The outdated background start does not replace the new server
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(
+            status(&manager, "reloaded").await,
+            Some(ServerStatus::Running)
+        );
+        assert_eq!(manager.clients.read().await["reloaded"].config, new);
+        assert!(!manager.inactive.read().await.contains_key("reloaded"));
+    }
+
+    #[tokio::test]
+    async fn test_start_does_not_wait_for_other_servers() {
+        let _config_dir = config_dir().await;
+        let manager = McpManager::new();
+        let slow = fake_server("3");
+        catalog::update("slow", &slow, None, &[], &[]);
+        let mut quick = fake_server("0");
+        quick.startup = Startup::Lazy;
+
+        assert_eq!(
+            manager.activate_server("slow", "Slow", &slow).await,
+            Ok(true)
+        );
+        assert_eq!(
+            manager.activate_server("quick", "Quick", &quick).await,
+            Ok(false)
+        );
+        // Let the background start take its lock
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        tokio::time::timeout(Duration::from_secs(2), manager.list_tools("quick"))
+            .await
+            .expect("waited for the background start of another server")
+            .unwrap();
+        assert_eq!(status(&manager, "quick").await, Some(ServerStatus::Running));
+        assert_eq!(status(&manager, "slow").await, Some(ServerStatus::Starting));
+    }
+
+    #[tokio::test]
+    async fn test_load_during_load_applies_new_config() {
+        let _config_dir = config_dir().await;
+        let manager = Arc::new(McpManager::new());
+        let mut config = McpConfig::default();
+        config
+            .mcp_servers
+            .insert("first".to_string(), fake_server("1"));
+        config.save().unwrap();
+
+        let running = manager.clone();
+        let first_load = tokio::spawn(async move { running.load_servers().await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Changed while the first load is still starting its server
+        config
+            .mcp_servers
+            .insert("second".to_string(), fake_server("0"));
+        config.save().unwrap();
+        let loaded = manager.load_servers().await.unwrap();
+
+        assert_eq!(first_load.await.unwrap().unwrap(), vec!["first"]);
+        assert_eq!(loaded, vec!["second"]);
+        assert_eq!(
+            status(&manager, "second").await,
+            Some(ServerStatus::Running)
+        );
+    }
+
+    #[test]
+    fn trust_message_shows_everything_the_fingerprint_covers() {
+        let config: McpServerConfig = serde_json::from_value(serde_json::json!({
+            "command": "npx",
+            "args": ["-y", "server"],
+            "env": { "NODE_OPTIONS": "--require ./x.js", "KEY": "${env:AWS_SECRET_ACCESS_KEY}" },
+            "cwd": "/work/tools",
+            "inheritEnv": { "allowlist": ["PATH", "HOME"] },
+            "sandbox": { "read": ["/work"], "network": false }
+        }))
+        .unwrap();
+        let server = ProjectServer {
+            id: "proj_work_00000000_demo".to_string(),
+            name: "demo".to_string(),
+            display_name: "demo".to_string(),
+            workspace: PathBuf::from("/work"),
+            config,
+            fingerprint: String::new(),
+        };
+
+        let message = trust_message(&server);
+        assert!(message.contains("Command: npx -y server"));
+        assert!(message.contains("Working directory: /work/tools"));
+        assert!(
+            message.contains("KEY=${env:AWS_SECRET_ACCESS_KEY}\n  NODE_OPTIONS=--require ./x.js")
+        );
+        assert!(message.contains("Inherited environment: only PATH, HOME"));
+        assert!(message.contains("Sandbox: read [/work], write [], network blocked"));
+    }
+}
@@ -1,7 +1,25 @@
+pub mod audit;
+pub mod catalog;
 pub mod client;
 pub mod commands;
 pub mod config;
+pub mod content;
+pub mod expand;
+pub mod framing;
+pub mod gateway;
+pub mod jsonrpc;
+pub mod limits;
 pub mod manager;
+pub mod monitor;
+pub mod naming;
+pub mod project;
+pub mod queue;
+pub mod recording;
+pub mod sandbox;
+pub mod schema;
+pub mod shell_env;
+pub mod stats;
+pub mod validate;
 
 pub use commands::*;
 pub use manager::McpManager;
@@ -0,0 +1,120 @@
+//! Live traffic of running servers for the MCP inspector window.
+//!
+//! Every client reports the JSON-RPC frames it exchanges with its server and
+//! the lines the server writes to stderr to the manager's [`Monitor`]. The app
+//! forwards them to the inspector as `mcp-traffic` and `mcp-stderr` events.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::recording::Direction;
+
+/// One frame or stderr line of a server
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum MonitorEvent<'a> {
+    Traffic {
+        server: &'a str,
+        dir: Direction,
+        message: &'a Value,
+        /// Unix time in milliseconds
+        time: u64,
+    },
+    Stderr {
+        server: &'a str,
+        line: &'a str,
+        time: u64,
+    },
+}
+
+impl MonitorEvent<'_> {
+    /// Name of the Tauri event the inspector listens to
+    pub fn name(&self) -> &'static str {
+        match self {
+            MonitorEvent::Traffic { .. } => "mcp-traffic",
+            MonitorEvent::Stderr { .. } => "mcp-stderr",
+        }
+    }
+}
+
+/// Called from the client's reader threads and request tasks, so it must not block
+pub type MonitorHandler = Arc<dyn Fn(&MonitorEvent) + Send + Sync>;
+
+/// Shared by the manager and its clients; events are dropped until a handler is set
+#[derive(Clone, Default)]
+pub struct Monitor {
+    handler: Arc<RwLock<Option<MonitorHandler>>>,
+}
+
+impl Monitor {
+    pub fn set_handler(&self, handler: MonitorHandler) {
+        *self.handler.write().unwrap() = Some(handler);
+    }
+
+    pub fn traffic(&self, server: &str, dir: Direction, message: &Value) {
+        self.emit(|time| MonitorEvent::Traffic {
+            server,
+            dir,
+            message,
+            time,
+        });
+    }
+
+    pub fn stderr(&self, server: &str, line: &str) {
+        self.emit(|time| MonitorEvent::Stderr { server, line, time });
+    }
+
+    fn emit<'a>(&self, event: impl FnOnce(u64) -> MonitorEvent<'a>) {
+        let handler = self.handler.read().unwrap().clone();
+        if let Some(handler) = handler {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            handler(&event(time));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_monitor_events() {
+        let monitor = Monitor::default();
+        // No handler yet: nothing to deliver to
+        monitor.stderr("fs", "starting");
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        monitor.set_handler(Arc::new(move |event| {
+            let mut payload = serde_json::to_value(event).unwrap();
+            payload["time"] = json!(0);
+            sink.lock().unwrap().push((event.name(), payload));
+        }));
+
+        monitor
+            .clone()
+            .traffic("fs", Direction::Out, &json!({"id": 1, "method": "ping"}));
+        monitor.stderr("fs", "ready");
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (
+                    "mcp-traffic",
+                    json!({"server": "fs", "dir": "out", "message": {"id": 1, "method": "ping"}, "time": 0})
+                ),
+                (
+                    "mcp-stderr",
+                    json!({"server": "fs", "line": "ready", "time": 0})
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Tool names as shown to the model.
+//!
+//! `serverSettings` can give every tool of a server a prefix, rename single
+//! tools (`alias`), replace their descriptions and disable them. Listings use
+//! the exposed names; tool calls are mapped back to the server's own names.
+
+use std::collections::HashSet;
+
+use super::client::McpTool;
+use super::config::ServerSettings;
+
+/// Name the model sees for a server tool.
+pub fn exposed_name(settings: Option<&ServerSettings>, tool: &str) -> String {
+    let Some(settings) = settings else {
+        return tool.to_string();
+    };
+    if let Some(alias) = settings
+        .tools
+        .get(tool)
+        .and_then(|t| t.alias.as_deref())
+        .filter(|a| !a.is_empty())
+    {
+        return alias.to_string();
+    }
+    match &settings.prefix {
+        Some(prefix) => format!("{}{}", prefix, tool),
+        None => tool.to_string(),
+    }
+}
+
+fn is_enabled(settings: Option<&ServerSettings>, tool: &str) -> bool {
+    match settings.and_then(|s| s.tools.get(tool)) {
+        Some(tool) => tool.enabled,
+        None => true,
+    }
+}
+
+/// Enabled tools with their exposed names. If two tools end up with the
+/// same name, the first one is kept.
+fn visible<'a>(
+    settings: Option<&ServerSettings>,
+    tools: &'a [McpTool],
+) -> Vec<(String, &'a McpTool)> {
+    let mut seen = HashSet::new();
+    tools
+        .iter()
+        .filter(|tool| is_enabled(settings, &tool.name))
+        .map(|tool| (exposed_name(settings, &tool.name), tool))
+        .filter(|(name, _)| seen.insert(name.clone()))
+        .collect()
+}
+
+/// Applies renames, description overrides and toggles to a server's tools.
+pub fn apply(server: &str, settings: Option<&ServerSettings>, tools: &[McpTool]) -> Vec<McpTool> {
+    let visible = visible(settings, tools);
+
+    for tool in tools {
+        if is_enabled(settings, &tool.name) && !visible.iter().any(|(_, t)| std::ptr::eq(*t, tool))
+        {
+            tracing::warn!(
+                "Hiding tool '{}' of '{}' - name '{}' is already used",
+                tool.name,
+                server,
+                exposed_name(settings, &tool.name)
+            );
+        }
+    }
+
+    visible
+        .into_iter()
+        .map(|(name, tool)| {
+            let mut tool = tool.clone();
+            if let Some(description) = settings
+                .and_then(|s| s.tools.get(&tool.name))
+                .and_then(|t| t.description.clone())
+            {
+                tool.description = Some(description);
+            }
+            tool.name = name;
+            tool
+        })
+        .collect()
+}
+
+/// Maps an exposed tool name back to the server's name for it.
+///
+/// Names that are not in `tools` (e.g. the list could not be read) are
+/// passed through unless they refer to a disabled tool.
+pub fn resolve(
+    server: &str,
+    settings: Option<&ServerSettings>,
+    tools: &[McpTool],
+    requested: &str,
+) -> Result<String, String> {
+    if let Some((_, tool)) = visible(settings, tools)
+        .into_iter()
+        .find(|(name, _)| name == requested)
+    {
+        return Ok(tool.name.clone());
+    }
+
+    if !is_enabled(settings, requested) {
+        return Err(format!(
+            "Tool '{}' on server '{}' is disabled",
+            requested, server
+        ));
+    }
+    // A listed tool that is exposed under another name
+    if tools.iter().any(|t| t.name == requested) {
+        return Err(format!(
+            "Tool '{}' on server '{}' is not available under this name",
+            requested, server
+        ));
+    }
+    Ok(requested.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::config::ToolSettings;
+    use serde_json::json;
+
+    fn tools(names: &[&str]) -> Vec<McpTool> {
+        names
+            .iter()
+            .map(|name| McpTool {
+                name: name.to_string(),
+                title: None,
+                description: Some(format!("{} tool", name)),
+                input_schema: json!({"type": "object"}),
+                output_schema: None,
+                annotations: None,
+            })
+            .collect()
+    }
+
+    fn settings() -> ServerSettings {
+        let mut settings = ServerSettings {
+            prefix: Some("gh_".to_string()),
+            ..Default::default()
+        };
+        settings.tools.insert(
+            "search_code".to_string(),
+            ToolSettings {
+                alias: Some("code_search".to_string()),
+                description: Some("Search code on GitHub".to_string()),
+                ..Default::default()
+            },
+        );
+        settings.tools.insert(
+            "delete_repo".to_string(),
+            ToolSettings {
+                enabled: false,
+                ..Default::default()
+            },
+        );
+        settings
+    }
+
+    #[test]
+    fn test_apply() {
+        let settings = settings();
+        let listed = apply(
+            "github",
+            Some(&settings),
+            &tools(&["search", "search_code", "delete_repo"]),
+        );
+        let names: Vec<_> = listed.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["gh_search", "code_search"]);
+        assert_eq!(
+            listed[1].description.as_deref(),
+            Some("Search code on GitHub")
+        );
+
+        // No settings: unchanged
+        assert_eq!(apply("s", None, &tools(&["search"]))[0].name, "search");
+    }
+
+    #[test]
+    fn test_apply_hides_collisions() {
+        let mut settings = ServerSettings::default();
+        settings.tools.insert(
+            "b".to_string(),
+            ToolSettings {
+                alias: Some("a".to_string()),
+                ..Default::default()
+            },
+        );
+        let listed = apply("s", Some(&settings), &tools(&["a", "b"]));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].description.as_deref(), Some("a tool"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let settings = settings();
+        let list = tools(&["search", "search_code", "delete_repo"]);
+        let resolve = |name| resolve("github", Some(&settings), &list, name);
+
+        assert_eq!(resolve("gh_search").unwrap(), "search");
+        assert_eq!(resolve("code_search").unwrap(), "search_code");
+        assert!(resolve("delete_repo").is_err());
+        // Original names of renamed tools are not callable
+        assert!(resolve("search").is_err());
+        // Tools missing from the list are passed through
+        assert_eq!(resolve("unlisted").unwrap(), "unlisted");
+    }
+}
@@ -0,0 +1,441 @@
+//! Project-scoped MCP servers from `.mcp.json` files.
+//!
+//! Workspace directories registered in `claude_desktop_config.json` are
+//! scanned for a `.mcp.json` file (same `mcpServers` format as the main
+//! config). Only stdio servers are used; other entries are skipped. Their
+//! servers are merged with these rules:
+//!
+//! - Servers are scoped as `proj_<directory>_<hash>_<name>`, where `<hash>` is
+//!   a short hash of the full workspace path, so they never replace user-level
+//!   servers or servers from another workspace with the same directory name.
+//! - A user-level server with the same name takes precedence; the project
+//!   server is skipped.
+//! - If a workspace is registered twice, the first entry wins.
+//! - `cwd` defaults to the workspace directory, and a relative `cwd` is
+//!   resolved against it.
+//!
+//! Project servers only start after the user trusts them. Decisions are
+//! stored per workspace and server together with a fingerprint of the
+//! server config, so a changed definition is asked about again.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::config::{McpConfig, McpServerConfig};
+
+pub const PROJECT_CONFIG_FILE: &str = ".mcp.json";
+
+/// A server defined in a workspace's `.mcp.json`
+#[derive(Debug, Clone)]
+pub struct ProjectServer {
+    /// Scoped server name used by the manager (`proj_<directory>_<hash>_<name>`)
+    pub id: String,
+    /// Name as written in `.mcp.json`
+    pub name: String,
+    pub display_name: String,
+    pub workspace: PathBuf,
+    pub config: McpServerConfig,
+    pub fingerprint: String,
+}
+
+/// Trust decision for a project server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    Trusted,
+    Denied,
+    /// Never seen, or its config changed since the last decision
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustEntry {
+    fingerprint: String,
+    trusted: bool,
+}
+
+/// Stored trust decisions (`mcp-trust.json`): workspace path -> server name -> decision
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(flatten)]
+    workspaces: HashMap<String, HashMap<String, TrustEntry>>,
+}
+
+impl TrustStore {
+    pub fn path() -> PathBuf {
+        McpConfig::config_path()
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("mcp-trust.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize trust store: {}", e))?;
+        std::fs::write(&path, content).map_err(|e| format!("Failed to write trust store: {}", e))
+    }
+
+    pub fn check(&self, server: &ProjectServer) -> Trust {
+        let workspace = server.workspace.to_string_lossy();
+        match self
+            .workspaces
+            .get(workspace.as_ref())
+            .and_then(|servers| servers.get(&server.name))
+        {
+            Some(entry) if entry.fingerprint == server.fingerprint => {
+                if entry.trusted {
+                    Trust::Trusted
+                } else {
+                    Trust::Denied
+                }
+            }
+            _ => Trust::Unknown,
+        }
+    }
+
+    pub fn set(&mut self, server: &ProjectServer, trusted: bool) {
+        self.workspaces
+            .entry(server.workspace.to_string_lossy().to_string())
+            .or_default()
+            .insert(
+                server.name.clone(),
+                TrustEntry {
+                    fingerprint: server.fingerprint.clone(),
+                    trusted,
+                },
+            );
+    }
+}
+
+/// Collects servers from all enabled workspaces, applying the precedence rules.
+pub fn collect_project_servers(config: &McpConfig) -> Vec<ProjectServer> {
+    let mut servers = Vec::new();
+    let mut seen = HashSet::new();
+
+    for workspace in config.workspaces.iter().filter(|w| w.enabled) {
+        let dir = PathBuf::from(&workspace.path);
+        let project_servers = match load_project_config(&dir) {
+            Ok(Some(c)) => c,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                continue;
+            }
+        };
+
+        let label = workspace_label(&dir);
+        let scope = workspace_scope(&dir);
+        let mut names: Vec<_> = project_servers.into_iter().collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, mut server_config) in names {
+            if config.mcp_servers.contains_key(&name) {
+                tracing::info!(
+                    "Skipping '{}' from {} - overridden by user config",
+                    name, workspace.path
+                );
+                continue;
+            }
+
+            let id = format!("proj_{}_{}", scope, name);
+            if !seen.insert(id.clone()) {
+                tracing::warn!(
+                    "Skipping '{}' from {} - '{}' already defined by another workspace",
+                    name, workspace.path, id
+                );
+                continue;
+            }
+
+            server_config.cwd = Some(match server_config.cwd.take() {
+                Some(cwd) => resolve_in(&dir, cwd),
+                None => dir.to_string_lossy().to_string(),
+            });
+            // The workspace is the server's root: writable inside the sandbox
+            if let Some(sandbox) = server_config.sandbox.as_mut() {
+                for path in sandbox.read.iter_mut().chain(sandbox.write.iter_mut()) {
+                    *path = resolve_in(&dir, std::mem::take(path));
+                }
+                sandbox.write.push(dir.to_string_lossy().to_string());
+            }
+
+            servers.push(ProjectServer {
+                display_name: format!("{} ({})", name, label),
+                fingerprint: fingerprint(&server_config),
+                id,
+                name,
+                workspace: dir.clone(),
+                config: server_config,
+            });
+        }
+    }
+
+    servers
+}
+
+/// Reads the stdio servers from `<dir>/.mcp.json`. Returns `Ok(None)` if the
+/// file does not exist.
+///
+/// Only `mcpServers` is read, entry by entry: other transports (`"type":
+/// "http"` etc.) and malformed entries are skipped with a warning instead of
+/// dropping the whole file.
+pub fn load_project_config(dir: &Path) -> Result<Option<HashMap<String, McpServerConfig>>, String> {
+    let path = dir.join(PROJECT_CONFIG_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let entries = match value.get("mcpServers") {
+        None | Some(serde_json::Value::Null) => return Ok(Some(HashMap::new())),
+        Some(serde_json::Value::Object(entries)) => entries,
+        Some(_) => {
+            return Err(format!(
+                "Failed to parse {}: mcpServers must be an object",
+                path.display()
+            ))
+        }
+    };
+
+    let mut servers = HashMap::new();
+    for (name, entry) in entries {
+        let transport = entry
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("stdio");
+        if transport != "stdio" {
+            tracing::warn!(
+                "Skipping '{}' from {} - unsupported transport '{}'",
+                name,
+                path.display(),
+                transport
+            );
+            continue;
+        }
+        match McpServerConfig::deserialize(entry) {
+            Ok(config) if config.command.is_empty() => {
+                tracing::warn!("Skipping '{}' from {} - no command", name, path.display());
+            }
+            Ok(config) => {
+                servers.insert(name.clone(), config);
+            }
+            Err(e) => {
+                tracing::warn!("Skipping '{}' from {} - {}", name, path.display(), e);
+            }
+        }
+    }
+    Ok(Some(servers))
+}
+
+/// Short, name-safe label for a workspace (its directory name).
+fn workspace_label(dir: &Path) -> String {
+    let name = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("workspace");
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Label plus a short hash of the full path, unique per workspace
+/// (`~/a/api` and `~/b/api` get different scopes).
+fn workspace_scope(dir: &Path) -> String {
+    let digest = Sha256::digest(dir.to_string_lossy().as_bytes());
+    let hash: String = digest
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}_{}", workspace_label(dir), hash)
+}
+
+/// Resolves a path from `.mcp.json` against the workspace directory.
+fn resolve_in(dir: &Path, path: String) -> String {
+    if Path::new(&path).is_absolute() || path.starts_with('~') || path.starts_with('$') {
+        path
+    } else {
+        dir.join(path).to_string_lossy().to_string()
+    }
+}
+
+/// Stable hash of everything that affects what gets executed.
+pub fn fingerprint(config: &McpServerConfig) -> String {
+    let env: BTreeMap<_, _> = config.env.iter().collect();
+    let mut canonical = serde_json::json!({
+        "command": config.command,
+        "args": config.args,
+        "env": env,
+        "cwd": config.cwd,
+        "inheritEnv": config.inherit_env,
+    });
+    // Only present when set, so existing trust decisions stay valid
+    if let Some(sandbox) = &config.sandbox {
+        canonical["sandbox"] = serde_json::json!(sandbox);
+    }
+    let digest = Sha256::digest(canonical.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::config::WorkspaceConfig;
+
+    /// Fixture directory of one test, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(test: &str) -> Self {
+            Self(std::env::temp_dir().join(format!(
+                "mcp-project-test-{}-{}",
+                std::process::id(),
+                test
+            )))
+        }
+
+        fn workspace(&self, name: &str, content: &str) -> PathBuf {
+            let dir = self.0.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(PROJECT_CONFIG_FILE), content).unwrap();
+            dir
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn config_with(workspaces: &[&Path]) -> McpConfig {
+        McpConfig {
+            workspaces: workspaces
+                .iter()
+                .map(|dir| WorkspaceConfig {
+                    path: dir.to_string_lossy().to_string(),
+                    enabled: true,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collect_project_servers() {
+        let temp = TempDir::new("collect");
+        let dir = temp.workspace(
+            "my.repo",
+            r#"{"mcpServers": {
+                "db": {"command": "node", "args": ["server.js"], "sandbox": {"write": ["out"]}},
+                "fs": {"command": "fs-server", "cwd": "tools"}
+            }}"#,
+        );
+
+        let mut config = config_with(&[&dir]);
+        config.mcp_servers.insert(
+            "fs".to_string(),
+            McpServerConfig {
+                command: "user-fs".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let servers = collect_project_servers(&config);
+        // "fs" is shadowed by the user-level server
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].id, format!("proj_{}_db", workspace_scope(&dir)));
+        assert!(servers[0].id.starts_with("proj_my_repo_"));
+        assert_eq!(servers[0].display_name, "db (my_repo)");
+        assert_eq!(
+            servers[0].config.cwd.as_deref(),
+            Some(dir.to_string_lossy().as_ref())
+        );
+        // Relative sandbox paths and the workspace itself are writable
+        assert_eq!(
+            servers[0].config.sandbox.as_ref().unwrap().write,
+            vec![
+                dir.join("out").to_string_lossy().to_string(),
+                dir.to_string_lossy().to_string()
+            ]
+        );
+
+        config.workspaces[0].enabled = false;
+        assert!(collect_project_servers(&config).is_empty());
+    }
+
+    #[test]
+    fn test_same_directory_name_in_two_places() {
+        let temp = TempDir::new("same-name");
+        let content = r#"{"mcpServers": {"db": {"command": "node"}}}"#;
+        let a = temp.workspace("a/api", content);
+        let b = temp.workspace("b/api", content);
+
+        let servers = collect_project_servers(&config_with(&[&a, &b]));
+        assert_eq!(servers.len(), 2);
+        assert_ne!(servers[0].id, servers[1].id);
+        assert_eq!(servers[0].display_name, "db (api)");
+        assert_eq!(servers[1].workspace, b);
+
+        // The same workspace registered twice is only loaded once
+        assert_eq!(collect_project_servers(&config_with(&[&a, &a])).len(), 1);
+    }
+
+    #[test]
+    fn test_skips_unsupported_and_malformed_entries() {
+        let temp = TempDir::new("skip-entries");
+        let dir = temp.workspace(
+            "repo",
+            r#"{"mcpServers": {
+                "remote": {"type": "http", "url": "https://example.com/mcp"},
+                "broken": {"command": "node", "args": "not-a-list"},
+                "empty": {"args": ["x"]},
+                "db": {"type": "stdio", "command": "node"},
+                "fs": {"command": "fs-server"}
+            }, "other": 1}"#,
+        );
+
+        let servers = collect_project_servers(&config_with(&[&dir]));
+        let names: Vec<_> = servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["db", "fs"]);
+    }
+
+    #[test]
+    fn test_trust_store() {
+        let mut server = ProjectServer {
+            id: "proj_w_db".to_string(),
+            name: "db".to_string(),
+            display_name: "db (w)".to_string(),
+            workspace: PathBuf::from("/w"),
+            config: McpServerConfig::default(),
+            fingerprint: "a".to_string(),
+        };
+        let mut store = TrustStore::default();
+        assert_eq!(store.check(&server), Trust::Unknown);
+
+        store.set(&server, true);
+        assert_eq!(store.check(&server), Trust::Trusted);
+
+        // A changed definition needs a new decision
+        server.fingerprint = "b".to_string();
+        assert_eq!(store.check(&server), Trust::Unknown);
+
+        store.set(&server, false);
+        assert_eq!(store.check(&server), Trust::Denied);
+    }
+}
@@ -0,0 +1,241 @@
+//! Per-server request concurrency limit (`maxInFlight`).
+//!
+//! Requests beyond the limit wait in a FIFO queue (tokio's semaphore is fair),
+//! so a burst of parallel tool calls is served in arrival order instead of
+//! all hitting a single-threaded server at once. When `maxQueued` requests
+//! are already waiting, new requests fail immediately.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Queue length used when only `maxInFlight` is set
+pub const DEFAULT_MAX_QUEUED: usize = 32;
+
+/// Returned when the queue already holds `max_queued` waiting requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull {
+    pub max_in_flight: usize,
+    pub max_queued: usize,
+}
+
+/// Snapshot of a server's request queue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_in_flight: Option<usize>,
+    pub in_flight: usize,
+    pub queued: usize,
+    pub total_requests: u64,
+    /// Requests that had to wait for a free slot
+    pub waited_requests: u64,
+    pub rejected_requests: u64,
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    total_requests: u64,
+    waited_requests: u64,
+    rejected_requests: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+pub struct RequestQueue {
+    limit: Option<(Semaphore, usize, usize)>,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    counters: Mutex<Counters>,
+}
+
+/// A slot in the server's in-flight window, released on drop
+pub struct Slot<'a> {
+    _permit: Option<SemaphorePermit<'a>>,
+    in_flight: &'a AtomicUsize,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Decrements the queued count even if the waiting future is dropped
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RequestQueue {
+    /// `max_in_flight` of `None` or `0` means unlimited.
+    pub fn new(max_in_flight: Option<usize>, max_queued: Option<usize>) -> Self {
+        let limit = max_in_flight.filter(|n| *n > 0).map(|n| {
+            (
+                Semaphore::new(n),
+                n,
+                max_queued.unwrap_or(DEFAULT_MAX_QUEUED),
+            )
+        });
+        Self {
+            limit,
+            in_flight: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            counters: Mutex::new(Counters::default()),
+        }
+    }
+
+    /// Waits for a free slot, in arrival order.
+    pub async fn acquire(&self) -> Result<Slot<'_>, QueueFull> {
+        let Some((semaphore, max_in_flight, max_queued)) = &self.limit else {
+            self.counters.lock().unwrap().total_requests += 1;
+            return Ok(self.slot(None));
+        };
+
+        // Only take a slot directly if nobody is queued, to keep the order fair
+        if self.queued.load(Ordering::SeqCst) == 0 {
+            if let Ok(permit) = semaphore.try_acquire() {
+                self.counters.lock().unwrap().total_requests += 1;
+                return Ok(self.slot(Some(permit)));
+            }
+        }
+
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= *max_queued {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            let mut counters = self.counters.lock().unwrap();
+            counters.total_requests += 1;
+            counters.rejected_requests += 1;
+            return Err(QueueFull {
+                max_in_flight: *max_in_flight,
+                max_queued: *max_queued,
+            });
+        }
+
+        let waiting = Waiting(&self.queued);
+        let started = Instant::now();
+        let permit = semaphore
+            .acquire()
+            .await
+            .expect("request queue semaphore is never closed");
+        drop(waiting);
+
+        let waited = started.elapsed();
+        {
+            let mut counters = self.counters.lock().unwrap();
+            counters.total_requests += 1;
+            counters.waited_requests += 1;
+            counters.total_wait += waited;
+            counters.max_wait = counters.max_wait.max(waited);
+        }
+        Ok(self.slot(Some(permit)))
+    }
+
+    fn slot<'a>(&'a self, permit: Option<SemaphorePermit<'a>>) -> Slot<'a> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Slot {
+            _permit: permit,
+            in_flight: &self.in_flight,
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let counters = self.counters.lock().unwrap();
+        QueueStats {
+            max_in_flight: self.limit.as_ref().map(|(_, n, _)| *n),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+            total_requests: counters.total_requests,
+            waited_requests: counters.waited_requests,
+            rejected_requests: counters.rejected_requests,
+            total_wait_ms: counters.total_wait.as_millis() as u64,
+            max_wait_ms: counters.max_wait.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_unlimited() {
+        let queue = RequestQueue::new(None, None);
+        let a = queue.acquire().await.unwrap();
+        let b = queue.acquire().await.unwrap();
+        assert_eq!(queue.stats().in_flight, 2);
+        drop((a, b));
+        let stats = queue.stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.total_requests, 2);
+        assert_eq!(stats.max_in_flight, None);
+    }
+
+    #[tokio::test]
+    async fn test_limit_queue_and_reject() {
+        let queue = Arc::new(RequestQueue::new(Some(1), Some(1)));
+        let first = queue.acquire().await.unwrap();
+
+        // Second request waits in the queue
+        let q = queue.clone();
+        let waiter = tokio::spawn(async move {
+            let _slot = q.acquire().await.unwrap();
+        });
+        while queue.stats().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Third request is rejected because the queue is full
+        assert_eq!(
+            queue.acquire().await.err(),
+            Some(QueueFull {
+                max_in_flight: 1,
+                max_queued: 1
+            })
+        );
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(first);
+        waiter.await.unwrap();
+
+        let stats = queue.stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.total_requests, 3);
+        assert_eq!(stats.waited_requests, 1);
+        assert_eq!(stats.rejected_requests, 1);
+        assert!(stats.max_wait_ms >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_fifo_order() {
+        let queue = Arc::new(RequestQueue::new(Some(1), None));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let first = queue.acquire().await.unwrap();
+
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let (q, order) = (queue.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                let _slot = q.acquire().await.unwrap();
+                order.lock().unwrap().push(i);
+            }));
+            while queue.stats().queued <= i {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        drop(first);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+}
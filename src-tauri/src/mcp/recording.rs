@@ -0,0 +1,359 @@
+//! Recording and replay of MCP server traffic.
+//!
+//! With `record: true` in a server config, every JSON-RPC frame exchanged with
+//! the server is appended to `recordings/<server>-<time>.jsonl` next to the
+//! config file. The first line describes the server; each following line is
+//! `{"t": <ms since start>, "dir": "out" | "in", "message": ...}`, where `out`
+//! is sent to the server and `in` is received from it.
+//!
+//! `claude-desktop mcp replay <file>` acts as a stdio server that answers
+//! from a recording, so the app can be pointed at it instead of the original
+//! server. Requests are matched by method and params, then by method alone;
+//! notifications the server sent after a response are sent again after it.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use super::config::{McpConfig, McpServerConfig};
+use super::jsonrpc::{self, Id, Inbound, METHOD_NOT_FOUND};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent to the server
+    Out,
+    /// Received from the server
+    In,
+}
+
+pub fn recordings_dir() -> PathBuf {
+    McpConfig::config_path()
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("recordings")
+}
+
+/// Appends the frames of one server process to a recording file
+pub struct Recorder {
+    file: Mutex<File>,
+    started: Instant,
+    pub path: PathBuf,
+    /// Set after the first write error, which is the only one logged
+    failed: AtomicBool,
+}
+
+impl Recorder {
+    pub fn create(server: &str, config: &McpServerConfig) -> Result<Self, String> {
+        let dir = recordings_dir();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let file_name: String = server
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = dir.join(format!("{}-{}.jsonl", file_name, started_at));
+
+        // Frames hold tool arguments and results: readable by the user only
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        // No args: they may hold user_config secrets
+        let header = json!({
+            "server": server,
+            "command": config.command,
+            "started": started_at,
+        });
+        writeln!(file, "{}", header)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            started: Instant::now(),
+            path,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn record(&self, direction: Direction, message: &Value) {
+        let line = json!({
+            "t": self.started.elapsed().as_micros() as f64 / 1000.0,
+            "dir": direction,
+            "message": message,
+        });
+        // One write per line, so frames from the reader thread and from
+        // requests never interleave
+        let result = writeln!(self.file.lock().unwrap(), "{}", line);
+        if let Err(e) = result {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                tracing::warn!("Failed to write {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// A recorded request with the server's answer
+#[derive(Debug, Clone)]
+struct Exchange {
+    method: String,
+    params: Value,
+    /// The `result` or `error` member of the response
+    outcome: (&'static str, Value),
+    latency: Duration,
+    /// Notifications the server sent after the response
+    notifications: Vec<Value>,
+    used: bool,
+}
+
+/// What a replayed server sends for one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub delay: Duration,
+    pub messages: Vec<Value>,
+}
+
+pub struct Recording {
+    exchanges: Vec<Exchange>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        // Requests sent to the server: method, params, time sent
+        let mut requests: HashMap<Id, (String, Value, f64)> = HashMap::new();
+        let mut exchanges: Vec<Exchange> = Vec::new();
+        // Notifications received before the first response
+        let mut leading = Vec::new();
+
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Value = serde_json::from_str(line)
+                .map_err(|e| format!("Line {} is not valid JSON: {}", number + 1, e))?;
+            // The header has no direction
+            let Some(direction) = entry.get("dir").and_then(Value::as_str) else {
+                continue;
+            };
+            let t = entry.get("t").and_then(Value::as_f64).unwrap_or(0.0);
+            let messages = match entry.get("message") {
+                Some(Value::Array(batch)) => batch.clone(),
+                Some(message) => vec![message.clone()],
+                None => continue,
+            };
+
+            for message in messages {
+                match (direction, jsonrpc::classify(&message)) {
+                    ("out", Inbound::Request { id, method }) => {
+                        let params = message.get("params").cloned().unwrap_or(Value::Null);
+                        requests.insert(id, (method, params, t));
+                    }
+                    ("in", Inbound::Response { id, .. }) => {
+                        let request = requests
+                            .remove(&id)
+                            .or_else(|| id.alternate().and_then(|alt| requests.remove(&alt)));
+                        let Some((method, params, sent)) = request else {
+                            continue;
+                        };
+                        let outcome = match message.get("error").filter(|e| !e.is_null()) {
+                            Some(error) => ("error", error.clone()),
+                            None => ("result", message.get("result").cloned().unwrap_or_default()),
+                        };
+                        exchanges.push(Exchange {
+                            method,
+                            params,
+                            outcome,
+                            latency: Duration::from_micros(((t - sent).max(0.0) * 1000.0) as u64),
+                            notifications: Vec::new(),
+                            used: false,
+                        });
+                    }
+                    ("in", Inbound::Notification { .. }) => match exchanges.last_mut() {
+                        Some(exchange) => exchange.notifications.push(message),
+                        None => leading.push(message),
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(first) = exchanges.first_mut() {
+            first.notifications.splice(0..0, leading);
+        }
+        Ok(Self { exchanges })
+    }
+
+    /// The recorded answer to a request, with `id` in place of the recorded one.
+    ///
+    /// Each recorded response is used once while there are unused ones for the
+    /// method; after that the last one is repeated.
+    pub fn reply(&mut self, id: &Value, method: &str, params: &Value) -> Reply {
+        let index = self
+            .exchanges
+            .iter()
+            .position(|e| !e.used && e.method == method && e.params == *params)
+            .or_else(|| {
+                self.exchanges
+                    .iter()
+                    .position(|e| !e.used && e.method == method)
+            })
+            .or_else(|| self.exchanges.iter().rposition(|e| e.method == method));
+
+        let Some(index) = index else {
+            return Reply {
+                delay: Duration::ZERO,
+                messages: vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("No recorded response for {}", method)
+                    }
+                })],
+            };
+        };
+
+        let exchange = &mut self.exchanges[index];
+        exchange.used = true;
+        let (key, value) = &exchange.outcome;
+        let mut response = json!({ "jsonrpc": "2.0", "id": id });
+        response[*key] = value.clone();
+
+        let mut messages = vec![response];
+        messages.extend(exchange.notifications.iter().cloned());
+        Reply {
+            delay: exchange.latency,
+            messages,
+        }
+    }
+}
+
+/// Serves a recording on stdin and stdout until stdin is closed.
+///
+/// With `speed`, each response is delayed by the recorded latency divided by
+/// `speed`; otherwise responses are sent at once.
+pub async fn replay_stdio(mut recording: Recording, speed: Option<f64>) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let write_task = tauri::async_runtime::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = rx.recv().await {
+            let line = format!("{}\n", message);
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            tracing::warn!("Ignoring invalid input: {}", line);
+            continue;
+        };
+        // Notifications and responses from the client need no answer
+        let (Some(id), Some(method)) = (
+            message.get("id"),
+            message.get("method").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let reply = recording.reply(id, method, &params);
+
+        let tx = tx.clone();
+        let delay = speed.map(|s| reply.delay.div_f64(s)).unwrap_or_default();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            for message in reply.messages {
+                let _ = tx.send(message);
+            }
+        });
+    }
+
+    drop(tx);
+    let _ = write_task.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDING: &str = r#"{"server":"fs","command":"npx","started":0}
+{"t":0.5,"dir":"out","message":{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{}}}
+{"t":10.5,"dir":"in","message":{"jsonrpc":"2.0","id":1,"result":{"tools":[{"name":"read"}]}}}
+{"t":20,"dir":"out","message":{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"read","arguments":{"path":"a"}}}}
+{"t":21,"dir":"out","message":{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"read","arguments":{"path":"b"}}}}
+{"t":25,"dir":"in","message":{"jsonrpc":"2.0","id":"3","result":{"content":[{"type":"text","text":"B"}]}}}
+{"t":30,"dir":"in","message":[{"jsonrpc":"2.0","id":2,"error":{"code":-32000,"message":"busy"}},{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}]}
+"#;
+
+    #[test]
+    fn test_replay() {
+        let mut recording = Recording::parse(RECORDING).unwrap();
+
+        let reply = recording.reply(&json!(7), "tools/list", &json!({}));
+        assert_eq!(reply.delay, Duration::from_millis(10));
+        assert_eq!(
+            reply.messages,
+            vec![json!({"jsonrpc": "2.0", "id": 7, "result": {"tools": [{"name": "read"}]}})]
+        );
+
+        // Matched by params, not by order
+        let reply = recording.reply(
+            &json!("x"),
+            "tools/call",
+            &json!({"name": "read", "arguments": {"path": "a"}}),
+        );
+        assert_eq!(reply.messages[0]["error"]["message"], "busy");
+        assert_eq!(
+            reply.messages[1]["method"],
+            "notifications/tools/list_changed"
+        );
+
+        // Then by method; the last response is repeated once all are used
+        let other = json!({"name": "read", "arguments": {"path": "c"}});
+        let reply = recording.reply(&json!(9), "tools/call", &other);
+        assert_eq!(reply.messages[0]["result"]["content"][0]["text"], "B");
+        assert_eq!(reply.messages[0]["id"], 9);
+        let reply = recording.reply(&json!(10), "tools/call", &other);
+        assert_eq!(reply.messages[0]["error"]["message"], "busy");
+
+        let reply = recording.reply(&json!(11), "prompts/list", &Value::Null);
+        assert_eq!(reply.messages[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_invalid_recording() {
+        assert!(Recording::parse("{\"t\": 1, \"dir\": \"in\"").is_err());
+        assert!(Recording::parse("").unwrap().exchanges.is_empty());
+    }
+}
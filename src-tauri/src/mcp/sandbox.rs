@@ -0,0 +1,299 @@
+//! Optional sandbox for MCP server processes (Linux only).
+//!
+//! - File system access is limited with Landlock. System directories, the
+//!   server's executable and the `PATH` entries are readable; `/tmp`, `/dev`,
+//!   `cwd` and the configured `write` paths are writable. Everything else is
+//!   denied, including the rest of the home directory.
+//! - With `"network": false` the server runs in its own network namespace
+//!   (inside an unprivileged user namespace), which has no usable interface.
+//!
+//! The restrictions are applied in the child process between fork and exec,
+//! so they cover everything the server runs. When the kernel lacks Landlock
+//! or user namespaces, the server is not started.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::config::SandboxConfig;
+
+/// Readable by every sandboxed server
+const SYSTEM_READ: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/etc",
+    "/opt",
+    "/nix/store",
+    "/proc",
+    "/sys",
+];
+
+/// Writable by every sandboxed server
+const SYSTEM_WRITE: &[&str] = &["/tmp", "/dev"];
+
+/// Paths a sandboxed server may read and write.
+#[derive(Debug, Default, PartialEq)]
+pub struct AllowedPaths {
+    pub read: Vec<PathBuf>,
+    pub write: Vec<PathBuf>,
+}
+
+/// Collects the allowed paths for a server.
+///
+/// The directory of the resolved executable is readable, and so is its
+/// installation prefix when it lives in a `bin` directory (e.g.
+/// `~/.nvm/versions/node/v20/` for `npx`), so interpreters find their
+/// libraries. The home directory itself is never added implicitly.
+pub fn allowed_paths(
+    config: &SandboxConfig,
+    program: &Path,
+    search_path: &str,
+    cwd: Option<&str>,
+) -> AllowedPaths {
+    let home = dirs::home_dir();
+    let implicit =
+        |path: &Path| path.is_absolute() && path != Path::new("/") && Some(path) != home.as_deref();
+
+    let mut read: Vec<PathBuf> = SYSTEM_READ.iter().map(PathBuf::from).collect();
+    let program = std::fs::canonicalize(program).unwrap_or_else(|_| program.to_path_buf());
+    if let Some(dir) = program.parent() {
+        read.push(dir.to_path_buf());
+        if dir.file_name().is_some_and(|name| name == "bin") {
+            read.extend(dir.parent().filter(|p| implicit(p)).map(Path::to_path_buf));
+        }
+    }
+    read.extend(std::env::split_paths(search_path).filter(|p| implicit(p)));
+    read.extend(config.read.iter().map(PathBuf::from));
+
+    let mut write: Vec<PathBuf> = SYSTEM_WRITE.iter().map(PathBuf::from).collect();
+    write.extend(cwd.map(PathBuf::from));
+    write.extend(config.write.iter().map(PathBuf::from));
+
+    for paths in [&mut read, &mut write] {
+        let mut seen = std::collections::HashSet::new();
+        paths.retain(|p| seen.insert(p.clone()));
+    }
+    AllowedPaths { read, write }
+}
+
+/// Keeps the Landlock ruleset open until the process has been spawned.
+pub struct Prepared {
+    #[cfg(target_os = "linux")]
+    _ruleset: std::os::fd::OwnedFd,
+}
+
+/// Configures `cmd` to start inside the sandbox.
+#[cfg(target_os = "linux")]
+pub fn prepare(
+    cmd: &mut Command,
+    config: &SandboxConfig,
+    paths: &AllowedPaths,
+) -> Result<Prepared, String> {
+    use std::os::fd::{AsRawFd, OwnedFd};
+    use std::os::unix::process::CommandExt;
+
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, CompatLevel, Compatible, Ruleset, RulesetAttr,
+        RulesetCreatedAttr,
+    };
+
+    let abi = landlock_abi()?;
+    let ruleset: Option<OwnedFd> = Ruleset::default()
+        .set_compatibility(CompatLevel::HardRequirement)
+        .handle_access(AccessFs::from_all(abi))
+        .and_then(|r| r.create())
+        .and_then(|r| r.add_rules(path_beneath_rules(&paths.read, AccessFs::from_read(abi))))
+        .and_then(|r| r.add_rules(path_beneath_rules(&paths.write, AccessFs::from_all(abi))))
+        .map_err(|e| format!("failed to build sandbox rules: {}", e))?
+        .into();
+    let ruleset = ruleset.ok_or("failed to build sandbox rules: Landlock is not available")?;
+
+    let namespace = if config.network {
+        None
+    } else {
+        check_user_namespaces()?;
+        Some(IdMaps::current())
+    };
+
+    let ruleset_fd = ruleset.as_raw_fd();
+    // Only async-signal-safe calls here: this runs in the forked child
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(maps) = &namespace {
+                if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                maps.write()?;
+            }
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::syscall(libc::SYS_landlock_restrict_self, ruleset_fd, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    Ok(Prepared { _ruleset: ruleset })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn prepare(
+    _cmd: &mut Command,
+    _config: &SandboxConfig,
+    _paths: &AllowedPaths,
+) -> Result<Prepared, String> {
+    Err("sandbox is only supported on Linux".to_string())
+}
+
+/// The Landlock ABI of the running kernel.
+#[cfg(target_os = "linux")]
+fn landlock_abi() -> Result<landlock::ABI, String> {
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+
+    let version = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    Ok(match version {
+        v if v <= 0 => {
+            return Err(format!(
+                "sandbox requires Landlock, which this kernel does not provide ({}). \
+                 Linux 5.13 or newer with Landlock enabled (lsm=landlock) is needed",
+                std::io::Error::last_os_error()
+            ))
+        }
+        1 => landlock::ABI::V1,
+        2 => landlock::ABI::V2,
+        3 => landlock::ABI::V3,
+        4 => landlock::ABI::V4,
+        5 => landlock::ABI::V5,
+        _ => landlock::ABI::V6,
+    })
+}
+
+/// Fails early when unprivileged user namespaces are turned off.
+#[cfg(target_os = "linux")]
+fn check_user_namespaces() -> Result<(), String> {
+    let disabled = |path: &str| {
+        std::fs::read_to_string(path)
+            .map(|v| v.trim() == "0")
+            .unwrap_or(false)
+    };
+    if disabled("/proc/sys/kernel/unprivileged_userns_clone")
+        || disabled("/proc/sys/user/max_user_namespaces")
+    {
+        return Err(
+            "\"network\": false requires unprivileged user namespaces, which are disabled on this system"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// uid/gid mappings written by the child after entering the user namespace,
+/// so the server keeps seeing the user's own ids.
+#[cfg(target_os = "linux")]
+struct IdMaps {
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl IdMaps {
+    fn current() -> Self {
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Self {
+            uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+            gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+        }
+    }
+
+    fn write(&self) -> std::io::Result<()> {
+        write_proc(b"/proc/self/setgroups\0", b"deny")?;
+        write_proc(b"/proc/self/uid_map\0", &self.uid_map)?;
+        write_proc(b"/proc/self/gid_map\0", &self.gid_map)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_proc(path: &[u8], content: &[u8]) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(path.as_ptr() as *const libc::c_char, libc::O_WRONLY);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, content.as_ptr() as *const libc::c_void, content.len());
+        libc::close(fd);
+        if written < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_paths() {
+        let config = SandboxConfig {
+            read: vec!["/data/models".to_string()],
+            write: vec!["/data/out".to_string(), "/tmp".to_string()],
+            network: true,
+        };
+        let paths = allowed_paths(
+            &config,
+            Path::new("/nonexistent/node-v20/bin/node"),
+            "/usr/bin:/:/opt/tools/bin",
+            Some("/work/project"),
+        );
+
+        assert!(paths.read.contains(&PathBuf::from("/usr")));
+        // Executable directory and its installation prefix
+        assert!(paths
+            .read
+            .contains(&PathBuf::from("/nonexistent/node-v20/bin")));
+        assert!(paths.read.contains(&PathBuf::from("/nonexistent/node-v20")));
+        assert!(paths.read.contains(&PathBuf::from("/opt/tools/bin")));
+        assert!(paths.read.contains(&PathBuf::from("/data/models")));
+        // `/` in PATH does not open up the whole file system
+        assert!(!paths.read.contains(&PathBuf::from("/")));
+
+        assert!(paths.write.contains(&PathBuf::from("/work/project")));
+        assert!(paths.write.contains(&PathBuf::from("/data/out")));
+        assert_eq!(
+            paths
+                .write
+                .iter()
+                .filter(|p| *p == Path::new("/tmp"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_home_is_not_implicit() {
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let program = home.join("bin").join("server");
+        let paths = allowed_paths(
+            &SandboxConfig::default(),
+            &program,
+            &home.to_string_lossy(),
+            None,
+        );
+        assert!(paths.read.contains(&home.join("bin")));
+        assert!(!paths.read.contains(&home));
+    }
+}
@@ -0,0 +1,197 @@
+//! JSON Schema validation at the MCP boundary.
+//!
+//! Tool arguments are checked against the tool's `inputSchema` before they are
+//! sent, and `structuredContent` in results is checked against `outputSchema`
+//! when the server declares one. Schemas are compiled once, when the tool list
+//! is loaded. Schemas that fail to compile are logged and skipped so a sloppy
+//! server does not become unusable.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::client::McpTool;
+
+/// One failing location in a validated document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaIssue {
+    /// JSON Pointer into the instance (`""` is the root)
+    pub path: String,
+    pub message: String,
+}
+
+/// A compiled schema; `None` accepts anything.
+pub struct CompiledSchema(Option<jsonschema::Validator>);
+
+impl CompiledSchema {
+    /// A missing (`null`), empty or invalid schema accepts anything.
+    pub fn new(schema: &Value) -> Self {
+        if schema.is_null() || schema.as_object().is_some_and(|o| o.is_empty()) {
+            return Self(None);
+        }
+        match jsonschema::validator_for(schema) {
+            Ok(validator) => Self(Some(validator)),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid schema: {}", e);
+                Self(None)
+            }
+        }
+    }
+
+    /// Validates `instance`, collecting every failing path.
+    pub fn validate(&self, instance: &Value) -> Result<(), Vec<SchemaIssue>> {
+        let Some(validator) = &self.0 else {
+            return Ok(());
+        };
+        let issues: Vec<SchemaIssue> = validator
+            .iter_errors(instance)
+            .map(|e| SchemaIssue {
+                path: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect();
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// The compiled `inputSchema` and `outputSchema` of a tool
+pub struct ToolSchemas {
+    pub input: CompiledSchema,
+    /// Only set when the tool declares an `outputSchema`
+    pub output: Option<CompiledSchema>,
+}
+
+impl ToolSchemas {
+    pub fn new(tool: &McpTool) -> Self {
+        Self {
+            input: CompiledSchema::new(&tool.input_schema),
+            output: tool.output_schema.as_ref().map(CompiledSchema::new),
+        }
+    }
+}
+
+/// One line per issue, e.g. `/count: "x" is not of type "integer"`.
+pub fn format_issues(issues: &[SchemaIssue]) -> String {
+    issues
+        .iter()
+        .map(|i| {
+            let path = if i.path.is_empty() { "/" } else { &i.path };
+            format!("{}: {}", path, i.message)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Tool result reporting invalid arguments.
+///
+/// Returned as a normal result with `isError` (instead of a protocol error) so
+/// the model sees which paths failed and can retry with corrected arguments.
+pub fn invalid_arguments_result(tool: &str, issues: &[SchemaIssue]) -> Value {
+    json!({
+        "isError": true,
+        "content": [{
+            "type": "text",
+            "text": format!(
+                "Invalid arguments for tool '{}':\n{}",
+                tool,
+                format_issues(issues)
+            )
+        }],
+        "structuredContent": {
+            "error": "invalid_arguments",
+            "issues": issues
+        }
+    })
+}
+
+/// Checks a `tools/call` result against the tool's `outputSchema`.
+///
+/// Error results are not required to carry `structuredContent`.
+pub fn validate_result(
+    output_schema: &CompiledSchema,
+    result: &Value,
+) -> Result<(), Vec<SchemaIssue>> {
+    if result.get("isError").and_then(|v| v.as_bool()) == Some(true) {
+        return Ok(());
+    }
+    match result.get("structuredContent") {
+        Some(structured) => output_schema.validate(structured),
+        None => Err(vec![SchemaIssue {
+            path: String::new(),
+            message: "structuredContent is missing but the tool declares an outputSchema"
+                .to_string(),
+        }]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(schema: &Value, instance: &Value) -> Result<(), Vec<SchemaIssue>> {
+        CompiledSchema::new(schema).validate(instance)
+    }
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "count": {"type": "integer", "minimum": 1}
+            },
+            "required": ["path"]
+        })
+    }
+
+    #[test]
+    fn test_validate_arguments() {
+        assert!(validate(&schema(), &json!({"path": "/tmp", "count": 2})).is_ok());
+
+        let issues = validate(&schema(), &json!({"count": 0})).unwrap_err();
+        assert_eq!(issues.len(), 2);
+        assert!(issues
+            .iter()
+            .any(|i| i.path.is_empty() && i.message.contains("path")));
+        assert!(issues.iter().any(|i| i.path == "/count"));
+
+        // No schema, empty schema and uncompilable schema accept anything
+        assert!(validate(&Value::Null, &json!(1)).is_ok());
+        assert!(validate(&json!({}), &json!(1)).is_ok());
+        assert!(validate(&json!({"type": 12}), &json!(1)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_result() {
+        let output = CompiledSchema::new(&json!({
+            "type": "object",
+            "properties": {"total": {"type": "number"}},
+            "required": ["total"]
+        }));
+        assert!(validate_result(&output, &json!({"structuredContent": {"total": 3}})).is_ok());
+        assert!(validate_result(&output, &json!({"isError": true, "content": []})).is_ok());
+
+        let issues =
+            validate_result(&output, &json!({"structuredContent": {"total": "3"}})).unwrap_err();
+        assert_eq!(issues[0].path, "/total");
+        assert!(validate_result(&output, &json!({"content": []})).is_err());
+    }
+
+    #[test]
+    fn test_invalid_arguments_result() {
+        let issues = vec![SchemaIssue {
+            path: "/count".to_string(),
+            message: "0 is less than the minimum of 1".to_string(),
+        }];
+        let result = invalid_arguments_result("list", &issues);
+        assert_eq!(result["isError"], true);
+        assert_eq!(result["structuredContent"]["issues"][0]["path"], "/count");
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("/count: 0 is less than the minimum of 1"));
+    }
+}
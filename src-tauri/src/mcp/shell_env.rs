@@ -0,0 +1,275 @@
+//! Login-shell environment resolution.
+//!
+//! When the app is launched from a desktop menu it inherits the session's
+//! minimal environment, so runtimes installed through nvm, asdf, volta etc.
+//! are not on `PATH`. The user's login shell is queried once (with a timeout)
+//! and its `PATH` is merged into MCP server spawns.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(unix)]
+const RESOLVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+static LOGIN_ENV: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Starts resolving the login-shell environment in the background.
+///
+/// Spawns that happen before it finishes wait for the result.
+pub fn init() {
+    std::thread::spawn(|| {
+        let env = login_env();
+        tracing::info!("Resolved login shell environment ({} variables)", env.len());
+    });
+}
+
+/// The login shell's environment, empty if it could not be resolved.
+pub fn login_env() -> &'static HashMap<String, String> {
+    LOGIN_ENV.get_or_init(resolve_login_env)
+}
+
+/// A variable from the login shell's environment.
+pub fn login_var(name: &str) -> Option<String> {
+    login_env().get(name).cloned()
+}
+
+/// The login shell's `PATH` followed by the app's own `PATH`, without duplicates.
+pub fn merged_path() -> String {
+    let login = login_env().get("PATH").cloned().unwrap_or_default();
+    let current = std::env::var("PATH").unwrap_or_default();
+
+    let mut seen = std::collections::HashSet::new();
+    let entries: Vec<PathBuf> = std::env::split_paths(&login)
+        .chain(std::env::split_paths(&current))
+        .filter(|p| !p.as_os_str().is_empty() && seen.insert(p.clone()))
+        .collect();
+
+    std::env::join_paths(entries)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or(current)
+}
+
+#[cfg(unix)]
+fn resolve_login_env() -> HashMap<String, String> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+
+    const START: &str = "__CLAUDE_DESKTOP_ENV_START__";
+    const END: &str = "__CLAUDE_DESKTOP_ENV_END__";
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let script = format!("printf '{}'; env -0; printf '{}'", START, END);
+
+    // -i so rc files that only run for interactive shells (nvm in .bashrc) are loaded
+    let child = Command::new(&shell)
+        .args(["-l", "-i", "-c", &script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!("Failed to start login shell '{}': {}", shell, e);
+            return HashMap::new();
+        }
+    };
+
+    // Read stdout on a separate thread so a chatty rc file cannot fill the pipe
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        buf
+    });
+
+    let started = std::time::Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < RESOLVE_TIMEOUT => {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            _ => {
+                tracing::warn!(
+                    "Login shell '{}' did not finish within {}s, using app environment",
+                    shell,
+                    RESOLVE_TIMEOUT.as_secs()
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+                return HashMap::new();
+            }
+        }
+    }
+
+    let output = reader.join().unwrap_or_default();
+    parse_env_output(&String::from_utf8_lossy(&output), START, END)
+}
+
+#[cfg(not(unix))]
+fn resolve_login_env() -> HashMap<String, String> {
+    HashMap::new()
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_env_output(output: &str, start: &str, end: &str) -> HashMap<String, String> {
+    let Some(begin) = output.find(start) else {
+        return HashMap::new();
+    };
+    let body = &output[begin + start.len()..];
+    let body = match body.rfind(end) {
+        Some(i) => &body[..i],
+        None => return HashMap::new(),
+    };
+
+    body.split('\0')
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Resolves a bare command name against `search_path`, then against common
+/// runtime install locations.
+///
+/// Commands containing a path separator are returned unchanged. The error
+/// lists the `PATH` that was searched and the extra locations that were checked.
+#[cfg(unix)]
+pub fn resolve_command(command: &str, search_path: &str) -> Result<PathBuf, String> {
+    if command.contains('/') {
+        return Ok(PathBuf::from(command));
+    }
+
+    for dir in std::env::split_paths(search_path) {
+        let candidate = dir.join(command);
+        if is_executable(&candidate) {
+            return Ok(candidate);
+        }
+    }
+
+    let fallbacks = fallback_dirs();
+    for dir in &fallbacks {
+        let candidate = dir.join(command);
+        if is_executable(&candidate) {
+            return Ok(candidate);
+        }
+    }
+
+    Err(format!(
+        "command '{}' not found. Searched PATH: {} | Also checked: {}",
+        command,
+        search_path,
+        fallbacks
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn resolve_command(command: &str, _search_path: &str) -> Result<PathBuf, String> {
+    Ok(PathBuf::from(command))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Common per-user runtime locations that desktop sessions usually miss.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn fallback_dirs() -> Vec<PathBuf> {
+    let mut dirs_list = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        // nvm: newest installed node version first
+        let nvm = home.join(".nvm").join("versions").join("node");
+        if let Ok(entries) = std::fs::read_dir(&nvm) {
+            let mut versions: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            versions.sort_by(|a, b| compare_versions(b, a));
+            dirs_list.extend(versions.into_iter().map(|v| v.join("bin")));
+        }
+
+        for rel in [
+            ".local/bin",
+            ".volta/bin",
+            ".asdf/shims",
+            ".local/share/mise/shims",
+            ".local/share/fnm/aliases/default/bin",
+            ".pyenv/shims",
+            ".cargo/bin",
+            ".bun/bin",
+            ".deno/bin",
+        ] {
+            dirs_list.push(home.join(rel));
+        }
+    }
+
+    for abs in ["/usr/local/bin", "/opt/homebrew/bin", "/snap/bin"] {
+        dirs_list.push(PathBuf::from(abs));
+    }
+
+    dirs_list
+}
+
+/// Orders `v20.1.0` style directory names numerically.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn compare_versions(a: &Path, b: &Path) -> std::cmp::Ordering {
+    let parse = |p: &Path| -> Vec<u64> {
+        p.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(a).cmp(&parse(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_output() {
+        let output = "motd noise\n__S__PATH=/a:/b\0HOME=/home/u\0MULTI=x\ny\0__E__";
+        let env = parse_env_output(output, "__S__", "__E__");
+        assert_eq!(env.get("PATH").unwrap(), "/a:/b");
+        assert_eq!(env.get("HOME").unwrap(), "/home/u");
+        assert_eq!(env.get("MULTI").unwrap(), "x\ny");
+        assert!(parse_env_output("no markers", "__S__", "__E__").is_empty());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let mut versions = [
+            PathBuf::from("v9.11.2"),
+            PathBuf::from("v20.1.0"),
+            PathBuf::from("v18.17.1"),
+        ];
+        versions.sort_by(|a, b| compare_versions(b, a));
+        assert_eq!(versions[0], PathBuf::from("v20.1.0"));
+        assert_eq!(versions[2], PathBuf::from("v9.11.2"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command() {
+        assert_eq!(
+            resolve_command("/usr/bin/custom", "").unwrap(),
+            PathBuf::from("/usr/bin/custom")
+        );
+        assert!(resolve_command("sh", "/bin:/usr/bin").is_ok());
+        let err = resolve_command("definitely-not-a-command", "/nonexistent").unwrap_err();
+        assert!(err.contains("Searched PATH: /nonexistent"));
+        assert!(err.contains("/usr/local/bin"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Runtime metrics per server and tool (`mcp_get_stats`).
+//!
+//! Call counts, errors, timeouts and latencies are kept in memory for the
+//! lifetime of the app. Percentiles are computed over the most recent
+//! `LATENCY_SAMPLES` calls. With `stats.snapshotIntervalSeconds` set, a
+//! snapshot is written to `mcp-stats.json` next to the config file.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::config::McpConfig;
+use crate::error::McpError;
+
+/// Latencies kept per server and per tool for percentiles
+const LATENCY_SAMPLES: usize = 1000;
+
+/// How a request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    /// JSON-RPC or transport error, or a tool result with `isError`
+    Error,
+    Timeout,
+}
+
+impl Outcome {
+    pub fn of(result: &Result<Value, McpError>) -> Self {
+        match result {
+            Ok(value) if value.get("isError").and_then(Value::as_bool) == Some(true) => {
+                Outcome::Error
+            }
+            Ok(_) => Outcome::Success,
+            Err(McpError::Timeout { .. }) => Outcome::Timeout,
+            Err(_) => Outcome::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CallStats {
+    pub calls: u64,
+    /// Failed calls, not counting timeouts
+    pub errors: u64,
+    pub timeouts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p50_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p90_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p99_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerStats {
+    pub running: bool,
+    /// Seconds since the current process started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_secs: Option<u64>,
+    /// Processes started for this server since the app started
+    pub starts: u32,
+    /// Starts after the first one (config changes, idle restarts, health check restarts)
+    pub restarts: u32,
+    /// Tool calls and resource reads
+    #[serde(flatten)]
+    pub calls: CallStats,
+    /// Tool calls by the server's tool name
+    pub tools: BTreeMap<String, CallStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsSnapshot {
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub servers: BTreeMap<String, ServerStats>,
+}
+
+#[derive(Default)]
+struct Counter {
+    calls: u64,
+    errors: u64,
+    timeouts: u64,
+    /// Most recent latencies in microseconds
+    latencies: VecDeque<u64>,
+}
+
+impl Counter {
+    fn record(&mut self, elapsed: Duration, outcome: Outcome) {
+        self.calls += 1;
+        match outcome {
+            Outcome::Success => {}
+            Outcome::Error => self.errors += 1,
+            Outcome::Timeout => self.timeouts += 1,
+        }
+        if self.latencies.len() == LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(elapsed.as_micros() as u64);
+    }
+
+    fn snapshot(&self) -> CallStats {
+        let mut sorted: Vec<u64> = self.latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: f64| {
+            // Nearest rank
+            let rank = ((p * sorted.len() as f64).ceil() as usize).max(1);
+            sorted.get(rank - 1).map(|&us| us as f64 / 1000.0)
+        };
+        CallStats {
+            calls: self.calls,
+            errors: self.errors,
+            timeouts: self.timeouts,
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: sorted.last().map(|&us| us as f64 / 1000.0),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ServerCounters {
+    starts: u32,
+    calls: Counter,
+    tools: HashMap<String, Counter>,
+}
+
+#[derive(Default)]
+pub struct Stats {
+    servers: Mutex<HashMap<String, ServerCounters>>,
+}
+
+impl Stats {
+    /// Records that a server process was started and initialized.
+    pub fn record_start(&self, server: &str) {
+        let mut servers = self.servers.lock().unwrap();
+        servers.entry(server.to_string()).or_default().starts += 1;
+    }
+
+    /// Records a request sent to a server; `tool` is set for tool calls.
+    pub fn record_call(
+        &self,
+        server: &str,
+        tool: Option<&str>,
+        elapsed: Duration,
+        outcome: Outcome,
+    ) {
+        let mut servers = self.servers.lock().unwrap();
+        let counters = servers.entry(server.to_string()).or_default();
+        counters.calls.record(elapsed, outcome);
+        if let Some(tool) = tool {
+            counters
+                .tools
+                .entry(tool.to_string())
+                .or_default()
+                .record(elapsed, outcome);
+        }
+    }
+
+    /// Current metrics. `uptimes` holds the running servers.
+    pub fn snapshot(&self, uptimes: &HashMap<String, Duration>) -> StatsSnapshot {
+        let mut servers: BTreeMap<String, ServerStats> = self
+            .servers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, counters)| {
+                let stats = ServerStats {
+                    starts: counters.starts,
+                    restarts: counters.starts.saturating_sub(1),
+                    calls: counters.calls.snapshot(),
+                    tools: counters
+                        .tools
+                        .iter()
+                        .map(|(tool, counter)| (tool.clone(), counter.snapshot()))
+                        .collect(),
+                    ..Default::default()
+                };
+                (name.clone(), stats)
+            })
+            .collect();
+
+        for (name, uptime) in uptimes {
+            let stats = servers.entry(name.clone()).or_default();
+            stats.running = true;
+            stats.uptime_secs = Some(uptime.as_secs());
+        }
+
+        StatsSnapshot {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            servers,
+        }
+    }
+}
+
+pub fn snapshot_path() -> PathBuf {
+    McpConfig::config_path()
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mcp-stats.json")
+}
+
+/// Writes a snapshot to `mcp-stats.json`, replacing the previous one.
+pub fn write_snapshot(snapshot: &StatsSnapshot) -> Result<(), String> {
+    let path = snapshot_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| format!("Failed to serialize stats: {}", e))?;
+    // Write to a temporary file first so readers never see a partial snapshot
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content).map_err(|e| format!("Failed to write stats: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write stats: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_outcome() {
+        assert_eq!(Outcome::of(&Ok(json!({"content": []}))), Outcome::Success);
+        assert_eq!(
+            Outcome::of(&Ok(json!({"content": [], "isError": true}))),
+            Outcome::Error
+        );
+        assert_eq!(
+            Outcome::of(&Err(McpError::Timeout {
+                method: "tools/call".to_string(),
+                timeout_secs: 30
+            })),
+            Outcome::Timeout
+        );
+        assert_eq!(Outcome::of(&Err(McpError::Cancelled)), Outcome::Error);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let stats = Stats::default();
+        stats.record_start("fs");
+        stats.record_start("fs");
+        for ms in 1..=100 {
+            stats.record_call(
+                "fs",
+                Some("read_file"),
+                Duration::from_millis(ms),
+                Outcome::Success,
+            );
+        }
+        stats.record_call("fs", None, Duration::from_millis(5), Outcome::Error);
+        stats.record_call(
+            "fs",
+            Some("write_file"),
+            Duration::from_secs(30),
+            Outcome::Timeout,
+        );
+
+        let uptimes = HashMap::from([("fs".to_string(), Duration::from_secs(42))]);
+        let snapshot = stats.snapshot(&uptimes);
+        let fs = &snapshot.servers["fs"];
+        assert!(fs.running);
+        assert_eq!(fs.uptime_secs, Some(42));
+        assert_eq!((fs.starts, fs.restarts), (2, 1));
+        assert_eq!(
+            (fs.calls.calls, fs.calls.errors, fs.calls.timeouts),
+            (102, 1, 1)
+        );
+
+        let read_file = &fs.tools["read_file"];
+        assert_eq!(read_file.calls, 100);
+        assert_eq!(read_file.p50_ms, Some(50.0));
+        assert_eq!(read_file.p90_ms, Some(90.0));
+        assert_eq!(read_file.p99_ms, Some(99.0));
+        assert_eq!(read_file.max_ms, Some(100.0));
+        assert_eq!(fs.tools["write_file"].timeouts, 1);
+    }
+
+    #[test]
+    fn test_latency_window() {
+        let mut counter = Counter::default();
+        for _ in 0..LATENCY_SAMPLES {
+            counter.record(Duration::from_secs(1), Outcome::Success);
+        }
+        counter.record(Duration::from_millis(1), Outcome::Success);
+        assert_eq!(counter.latencies.len(), LATENCY_SAMPLES);
+        assert_eq!(counter.snapshot().calls, LATENCY_SAMPLES as u64 + 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! Config file checks for `claude-desktop config validate`.
+//!
+//! Reports what would make servers fail to start (unresolved variables,
+//! commands not on `PATH`, missing working directories) and references to
+//! servers or profiles that do not exist, without starting anything.
+
+use serde::Serialize;
+use std::path::Path;
+
+use super::config::McpConfig;
+use super::expand::expand_config;
+use super::project;
+use super::shell_env;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The config cannot be loaded or a server cannot start
+    Error,
+    /// Likely a mistake, but nothing fails because of it
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Where the problem is, e.g. `mcpServers.filesystem`
+    pub scope: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub path: String,
+    /// No issue with severity `error`
+    pub valid: bool,
+    pub issues: Vec<Issue>,
+}
+
+/// Loads and checks the config file.
+pub fn validate_config_file() -> Report {
+    let path = McpConfig::config_path();
+    let issues = match McpConfig::load() {
+        Ok(config) => validate(&config),
+        Err(e) => vec![Issue {
+            severity: Severity::Error,
+            scope: "file".to_string(),
+            message: e.to_string(),
+        }],
+    };
+    Report {
+        path: path.to_string_lossy().to_string(),
+        valid: !issues.iter().any(|i| i.severity == Severity::Error),
+        issues,
+    }
+}
+
+pub fn validate(config: &McpConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut error = |scope: String, message: String| {
+        issues.push(Issue {
+            severity: Severity::Error,
+            scope,
+            message,
+        })
+    };
+
+    let mut names: Vec<&String> = config.mcp_servers.keys().collect();
+    names.sort();
+    for name in names {
+        let server = &config.mcp_servers[name];
+        if server.disabled {
+            continue;
+        }
+        let scope = format!("mcpServers.{}", name);
+        let mut server = server.clone();
+        config.apply_profile_env(&mut server);
+        let expanded = match expand_config(&server) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                error(scope, e);
+                continue;
+            }
+        };
+        let search_path = expanded
+            .env
+            .get("PATH")
+            .cloned()
+            .unwrap_or_else(shell_env::merged_path);
+        if let Err(e) = shell_env::resolve_command(&expanded.command, &search_path) {
+            error(scope.clone(), e);
+        }
+        if let Some(cwd) = &expanded.cwd {
+            if !Path::new(cwd).is_dir() {
+                error(scope, format!("working directory '{}' does not exist", cwd));
+            }
+        }
+    }
+
+    if let Some(active) = &config.active_profile {
+        if !config.profiles.contains_key(active) {
+            error(
+                "activeProfile".to_string(),
+                format!("profile '{}' is not defined", active),
+            );
+        }
+    }
+
+    for workspace in config.workspaces.iter().filter(|w| w.enabled) {
+        let scope = format!("workspaces.{}", workspace.path);
+        let dir = Path::new(&workspace.path);
+        if !dir.is_dir() {
+            issues.push(Issue {
+                severity: Severity::Warning,
+                scope,
+                message: "directory does not exist".to_string(),
+            });
+        } else if let Err(e) = project::load_project_config(dir) {
+            issues.push(Issue {
+                severity: Severity::Error,
+                scope,
+                message: e,
+            });
+        }
+    }
+
+    // Project (`proj_*`) and extension (`ext_*`) servers are only known once loaded
+    let is_known = |name: &str| {
+        config.mcp_servers.contains_key(name)
+            || name.starts_with("proj_")
+            || name.starts_with("ext_")
+    };
+    let mut profiles: Vec<_> = config.profiles.iter().collect();
+    profiles.sort_by_key(|(name, _)| name.as_str());
+    for (profile_name, profile) in profiles {
+        for server in profile.servers.iter().filter(|s| !is_known(s)) {
+            issues.push(Issue {
+                severity: Severity::Warning,
+                scope: format!("profiles.{}", profile_name),
+                message: format!("server '{}' is not configured", server),
+            });
+        }
+    }
+    let mut settings: Vec<&String> = config.server_settings.keys().collect();
+    settings.sort();
+    for server in settings.into_iter().filter(|s| !is_known(s)) {
+        issues.push(Issue {
+            severity: Severity::Warning,
+            scope: format!("serverSettings.{}", server),
+            message: format!("server '{}' is not configured", server),
+        });
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config: McpConfig = serde_json::from_value(serde_json::json!({
+            "mcpServers": {
+                "ok": { "command": "/bin/sh" },
+                "missing": { "command": "surely-not-an-installed-command" },
+                "unresolved": { "command": "/bin/sh", "args": ["${env:SURELY_UNSET_VARIABLE}"] },
+                "off": { "command": "surely-not-an-installed-command", "disabled": true }
+            },
+            "profiles": { "work": { "servers": ["ok", "typo", "proj_app_db"] } },
+            "activeProfile": "home",
+            "serverSettings": { "gone": {} }
+        }))
+        .unwrap();
+
+        let issues = validate(&config);
+        let scopes: Vec<(&str, Severity)> = issues
+            .iter()
+            .map(|i| (i.scope.as_str(), i.severity))
+            .collect();
+        #[cfg(unix)]
+        assert!(scopes.contains(&("mcpServers.missing", Severity::Error)));
+        assert!(scopes.contains(&("mcpServers.unresolved", Severity::Error)));
+        assert!(scopes.contains(&("activeProfile", Severity::Error)));
+        assert!(scopes.contains(&("profiles.work", Severity::Warning)));
+        assert!(scopes.contains(&("serverSettings.gone", Severity::Warning)));
+        assert!(!scopes
+            .iter()
+            .any(|(s, _)| *s == "mcpServers.ok" || *s == "mcpServers.off"));
+        // Only the unknown name is reported
+        assert_eq!(
+            issues.iter().filter(|i| i.scope == "profiles.work").count(),
+            1
+        );
+    }
+}
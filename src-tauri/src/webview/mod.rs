@@ -11,6 +11,9 @@
 //! 4. `04_mcp_bridge.js` - MCP transport and JSON-RPC
 //! 5. `05_mcp_manager.js` - MCP server manager
 //! 6. `06_file_handling.js` - Drag-drop and clipboard support
+//!
+//! The scripts only run on remote pages; bundled pages (the MCP inspector)
+//! are skipped.
 
 use tauri::{
     plugin::{Builder, TauriPlugin},
@@ -73,7 +76,7 @@ window.__TAURI_ARCH__ = '{}';
     );
 
     // Concatenate all scripts in order
-    [
+    let scripts = [
         header.as_str(),
         scripts::POLYFILLS,
         scripts::FAKE_PORT,
@@ -82,7 +85,15 @@ window.__TAURI_ARCH__ = '{}';
         scripts::MCP_MANAGER,
         scripts::FILE_HANDLING,
     ]
-    .join("\n\n")
+    .join("\n\n");
+
+    // The plugin injects into every webview; bundled pages such as the MCP
+    // inspector are served from the app origin and must not get the claude.ai
+    // shims (`tauri://localhost`, or `http://tauri.localhost` on Windows)
+    format!(
+        "if (window.location.protocol !== 'tauri:' && window.location.hostname !== 'tauri.localhost') {{\n{}\n}}\n",
+        scripts
+    )
 }
 
 /// Creates the desktop-api plugin with JavaScript injection.
@@ -115,5 +126,7 @@ mod tests {
         assert!(script.contains("FakeMessagePort"));
         // Should be reasonably sized (original was ~130KB)
         assert!(script.len() > 100_000);
+        // Bundled pages are skipped
+        assert!(script.starts_with("if (window.location.protocol !== 'tauri:'"));
     }
 }
@@ -1,6 +1,22 @@
 // Claude Desktop API - 在頁面載入前注入
 window.isElectron = true;
 
+// === Tauri 指令錯誤 ===
+// 後端指令失敗時回傳 { code, message, data? }（例如 MCP_TIMEOUT、EXT_NOT_FOUND；
+// data 為伺服器 JSON-RPC 錯誤附帶的資料），
+// 其他例外（JS Error、字串）也轉成相同格式，呼叫端只需依 code 分支
+window.__tauriError = function(err) {
+    if (err && typeof err === 'object' && typeof err.code === 'string') {
+        var error = { code: err.code, message: err.message || err.code };
+        if (err.data !== undefined) error.data = err.data;
+        return error;
+    }
+    return {
+        code: 'UNKNOWN_ERROR',
+        message: err && err.message ? err.message : String(err)
+    };
+};
+
 // === 靜默阻擋被 CSP 限制的遙測請求 ===
 // claude.ai 的 CSP 阻擋了 a-api.anthropic.com（遙測/分析 API）
 // 這不是核心功能，我們直接靜默忽略這些請求，避免 console 錯誤
@@ -305,7 +321,8 @@ window.isElectron = true;
         } catch (err) {
             var elapsed = Date.now() - startTime;
             console.error('[MCP Test] FAILED in', elapsed, 'ms');
-            console.error('[MCP Test] Error:', err);
+            var error = window.__tauriError(err);
+            console.error('[MCP Test] Error:', error.code, error.message);
         }
         console.log('[MCP Test] ======================================');
     }
@@ -615,6 +615,8 @@ var _claudeAppBindingsImpl = {
 
             for (var j = 0; j < servers.length; j++) {
                 var server = servers[j];
+                // 已停用或未受信任的伺服器不顯示在 Connectors 選單
+                if (server.status === 'disabled' || server.status === 'untrusted') continue;
                 // 使用 display_name 作為顯示名稱（如 "Filesystem"），server.name 是內部 ID（如 "ext_ant.dir..."）
                 var displayName = server.display_name || server.name;
                 var internalName = server.name;  // 內部 ID，用於 MCP 通訊
@@ -624,15 +626,16 @@ var _claudeAppBindingsImpl = {
                     status: 'connected',
                     error: null,
                     tools: server.tools.map(function(t) {
-                        return {
+                        var toolData = {
                             name: t.name,
                             description: t.description || '',
                             inputSchema: t.input_schema || { type: 'object', properties: {} },
-                            annotations: {
-                                audience: ['user'],
-                                priority: 0
-                            }
+                            // 使用伺服器宣告的 annotations（readOnlyHint、destructiveHint 等）
+                            annotations: t.annotations || {}
                         };
+                        if (t.title) toolData.title = t.title;
+                        if (t.output_schema) toolData.outputSchema = t.output_schema;
+                        return toolData;
                     }),
                     resources: server.resources || [],
                     resourceTemplates: [],
@@ -1134,8 +1137,9 @@ window.__CLAUDE_DESKTOP_MCP__ = {
             console.log('[MCP DEBUG 21] Result preview:', JSON.stringify(result).substring(0, 200));
             return result;
         } catch (e) {
-            console.error('[MCP DEBUG 21] callTool EXCEPTION:', e);
-            return { error: e.toString() };
+            var error = window.__tauriError(e);
+            console.error('[MCP DEBUG 21] callTool EXCEPTION:', error.code, error.message);
+            return { error: error.message, code: error.code, data: error.data };
         }
     },
 
@@ -1147,8 +1151,9 @@ window.__CLAUDE_DESKTOP_MCP__ = {
                 uri: uri
             });
         } catch (e) {
-            console.error('[__CLAUDE_DESKTOP_MCP__] readResource error:', e);
-            return { error: e.toString() };
+            var error = window.__tauriError(e);
+            console.error('[__CLAUDE_DESKTOP_MCP__] readResource error:', error.code, error.message);
+            return { error: error.message, code: error.code, data: error.data };
         }
     },
 
@@ -1207,6 +1212,13 @@ window.__CLAUDE_DESKTOP_MCP__ = {
 
         try {
             var result = await this.callTool(toolInfo.server, toolName, input);
+            if (result && result.error) {
+                return {
+                    error: true,
+                    code: result.code,
+                    message: result.error
+                };
+            }
             return {
                 error: false,
                 result: result
@@ -71,6 +71,36 @@ window.__mcpChannels = {};          // serverName -> MessageChannel (共用)
 window.__mcpHandledRequests = window.__mcpHandledRequests || {};
 window.__mcpCachedResponses = window.__mcpCachedResponses || {};
 
+// 將後端錯誤（{ error, code }）轉成 JSON-RPC 回應
+// 被權限政策或使用者拒絕的工具呼叫以 isError 結果回傳，讓模型得知原因並繼續對話
+function mcpErrorResponse(id, failure) {
+    if (failure.code === 'MCP_PERMISSION_DENIED') {
+        return {
+            jsonrpc: '2.0',
+            id: id,
+            result: { content: [{ type: 'text', text: failure.error }], isError: true }
+        };
+    }
+    var rpcCode;
+    switch (failure.code) {
+        case 'MCP_TIMEOUT':
+            rpcCode = -32001;   // Request timeout（MCP SDK 使用的錯誤碼）
+            break;
+        case 'MCP_TOOL_UNAVAILABLE':
+            rpcCode = -32602;   // Invalid params：未知或已停用的工具
+            break;
+        default:
+            rpcCode = -32603;
+    }
+    var data = { code: failure.code };
+    if (failure.data !== undefined) data.data = failure.data;   // 伺服器 JSON-RPC 錯誤的 data
+    return {
+        jsonrpc: '2.0',
+        id: id,
+        error: { code: rpcCode, message: String(failure.error), data: data }
+    };
+}
+
 window.__handleMcpJsonRpc = async function(serverName, request) {
     console.log('[MCP JSON-RPC]', serverName, request);
 
@@ -143,6 +173,10 @@ window.__handleMcpJsonRpc = async function(serverName, request) {
                     console.log('[MCP DEBUG 21] result type:', typeof result, 'has content:', !!(result && result.content));
                     console.log('[MCP DEBUG 21] result preview:', JSON.stringify(result).substring(0, 300));
 
+                    if (result && result.error) {
+                        return mcpErrorResponse(id, result);
+                    }
+
                     // 方法 16：記錄 tools/call 成功次數，用於判斷是否觸發 auto-reconnect
                     if (window.__mcpActiveConnections && window.__mcpActiveConnections[serverName]) {
                         var conn = window.__mcpActiveConnections[serverName];
@@ -182,10 +216,14 @@ window.__handleMcpJsonRpc = async function(serverName, request) {
             case 'resources/read':
                 var uri = params.uri;
                 var readResult = await window.__CLAUDE_DESKTOP_MCP__.readResource(serverName, uri);
+                if (readResult && readResult.error) {
+                    return mcpErrorResponse(id, readResult);
+                }
+                // 伺服器已回傳 { contents: [{ uri, mimeType, text | blob }] }，直接轉交以保留 MIME 類型與二進位內容
                 return {
                     jsonrpc: '2.0',
                     id: id,
-                    result: { contents: [{ uri: uri, text: JSON.stringify(readResult) }] }
+                    result: readResult && readResult.contents ? readResult : { contents: [] }
                 };
 
             case 'prompts/list':
@@ -719,11 +757,12 @@ window['claude.settings'] = {
 
                 return result ? result.id : null;
             } catch (e) {
-                console.error('[Extensions] installDxtFromDirectory failed:', e);
-                // 發送錯誤進度事件
+                var error = window.__tauriError(e);
+                console.error('[Extensions] installDxtFromDirectory failed:', error.code, error.message);
+                // 發送錯誤進度事件（EXT_INVALID_PACKAGE / EXT_INVALID_MANIFEST 表示檔案本身有問題）
                 if (window.__extensionDownloadProgressCallbacks) {
                     window.__extensionDownloadProgressCallbacks.forEach(function(cb) {
-                        cb(extensionId, 'error', 0, 100, e.message);
+                        cb(extensionId, 'error', 0, 100, error.message);
                     });
                 }
                 return null;
@@ -826,7 +865,11 @@ window['claude.settings'] = {
                 console.log('[Extensions] Got manifest for', extensionId, ':', manifest);
                 return manifest;
             } catch (e) {
-                console.error('[Extensions] Failed to get manifest:', e);
+                var error = window.__tauriError(e);
+                // 尚未安裝的擴充功能沒有 manifest，不算錯誤
+                if (error.code !== 'EXT_NOT_FOUND') {
+                    console.error('[Extensions] Failed to get manifest:', error.code, error.message);
+                }
                 return null;
             }
         },
@@ -979,6 +1022,8 @@ window.__mcpServersLoaded = false;
 
         for (var idx = 0; idx < servers.length; idx++) {
             var server = servers[idx];
+            // 已停用或未受信任的伺服器不顯示在 Connectors 選單
+            if (server.status === 'disabled' || server.status === 'untrusted') continue;
             // 使用 display_name 作為顯示名稱（如 "Filesystem"），server.name 是內部 ID
             var displayName = server.display_name || server.name;
             var internalName = server.name;  // 內部 ID，用於 MCP 通訊
@@ -988,15 +1033,16 @@ window.__mcpServersLoaded = false;
                 status: 'connected',
                 error: null,
                 tools: server.tools.map(function(t) {
-                    return {
+                    var toolData = {
                         name: t.name,
                         description: t.description || '',
                         inputSchema: t.input_schema || { type: 'object', properties: {} },
-                        annotations: {
-                            audience: ['user'],
-                            priority: 0
-                        }
+                        // 使用伺服器宣告的 annotations（readOnlyHint、destructiveHint 等）
+                        annotations: t.annotations || {}
                     };
+                    if (t.title) toolData.title = t.title;
+                    if (t.output_schema) toolData.outputSchema = t.output_schema;
+                    return toolData;
                 }),
                 resources: server.resources || [],
                 resourceTemplates: [],
@@ -1171,3 +1217,25 @@ window.addEventListener('load', async function() {
     // 導致顯示 "Could not attach to MCP server" 錯誤
     // MCP 連線不需要 heartbeat，claude.ai 會在需要時自動重連
 });
+
+// 有快取目錄的伺服器會在背景啟動，先以快取的工具清單顯示；
+// 就緒後後端送出 mcp-servers-changed，重新取得清單並通知 claude.ai
+(async function listenMcpServerChanges() {
+    for (var i = 0; i < 100; i++) {
+        if (window.__TAURI__) break;
+        await new Promise(function(r) { setTimeout(r, 50); });
+    }
+    if (!window.__TAURI__ || !window.__TAURI__.event) return;
+
+    window.__TAURI__.event.listen('mcp-servers-changed', async function() {
+        console.log('[MCP] Servers changed, refreshing catalog...');
+        // 讓 listMcpServers 重新查詢而非回傳快取
+        window.__mcpServersLoaded = false;
+        try {
+            await window.claudeAppBindings.listMcpServers();
+            triggerMcpEvents(window.__mcpServersCache || {}, true);
+        } catch (e) {
+            console.error('[MCP] Failed to refresh servers:', e);
+        }
+    });
+})();
@@ -16,6 +16,33 @@ window.__toolResults = {};
 (function() {
     var originalFetch = window.fetch;
 
+    // 將 MCP 結果的 content 區塊轉換成 tool_result 格式
+    // 文字與圖片直接對應，其他區塊（audio、resource、resource_link）以文字描述
+    function toToolResultContent(result) {
+        if (!result || !Array.isArray(result.content)) {
+            return JSON.stringify(result);
+        }
+        return result.content.map(function(block) {
+            if (block.type === 'text') {
+                return { type: 'text', text: block.text };
+            }
+            if (block.type === 'image' && block.data) {
+                return {
+                    type: 'image',
+                    source: { type: 'base64', media_type: block.mimeType, data: block.data }
+                };
+            }
+            if (block.type === 'resource_link') {
+                return { type: 'text', text: '[' + (block.mimeType || 'resource') + '] ' + block.uri };
+            }
+            if (block.type === 'resource' && block.resource && block.resource.text !== undefined) {
+                return { type: 'text', text: block.resource.text };
+            }
+            var summary = { type: block.type, mimeType: block.mimeType || (block.resource && block.resource.mimeType) };
+            return { type: 'text', text: JSON.stringify(summary) };
+        });
+    }
+
     window.fetch = async function(input, init) {
         var url = (typeof input === 'string') ? input : (input.url || '');
         var method = (init && init.method) ? init.method.toUpperCase() : 'GET';
@@ -177,7 +204,7 @@ window.__toolResults = {};
                                         window.__toolResults[currentTool.id] = {
                                             tool_use_id: currentTool.id,
                                             type: 'tool_result',
-                                            content: JSON.stringify(result)
+                                            content: toToolResultContent(result)
                                         };
                                     }
 
@@ -200,6 +227,21 @@ window.__toolResults = {};
     console.log('[Fetch] Interceptor installed');
 })();
 
+// ========================================
+// MCP Inspector 快捷鍵（Ctrl+Shift+M / Cmd+Shift+M）
+// ========================================
+(function() {
+    document.addEventListener('keydown', function(event) {
+        if (!((event.ctrlKey || event.metaKey) && event.shiftKey && event.key.toLowerCase() === 'm')) return;
+        if (!window.__TAURI__) return;
+
+        event.preventDefault();
+        window.__TAURI__.core.invoke('mcp_open_inspector').catch(function(err) {
+            console.error('[MCP Inspector] Failed to open:', err);
+        });
+    });
+})();
+
 console.log('[Claude Desktop] All desktop APIs initialized');
 
 // ========================================
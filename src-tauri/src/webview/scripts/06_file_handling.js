@@ -277,7 +277,9 @@
                     var file = createFile(fileName, base64Data, mimeType);
                     files.push(file);
                 } catch (err) {
-                    console.error('[FileHandling] Error reading file:', filePath, err);
+                    var error = window.__tauriError(err);
+                    // FILE_NOT_FOUND：拖放後檔案已被移除
+                    console.error('[FileHandling] Error reading file:', filePath, error.code, error.message);
                 }
             }
 